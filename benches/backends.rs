@@ -0,0 +1,69 @@
+//! Compares the `Mutex`-based channel against the lock-free backend.
+//!
+//! Run with `cargo bench`.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+const MESSAGES: usize = 200_000;
+const ROUNDS: usize = 5;
+
+fn run(name: &str, producers: usize, mut round: impl FnMut(usize) -> Duration) {
+    let best = (0..ROUNDS).map(|_| round(producers)).min().unwrap();
+    let per_msg = best.as_nanos() as f64 / MESSAGES as f64;
+    println!(
+        "{:<10} producers={:<2} {:>10.2?} ({:.1} ns/msg)",
+        name, producers, best, per_msg
+    );
+}
+
+fn mutex_round(producers: usize) -> Duration {
+    let (tx, rx) = channels::channel();
+    let start = Instant::now();
+    let handles: Vec<_> = (0..producers)
+        .map(|_| {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..MESSAGES / producers {
+                    tx.send(i).unwrap();
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+    assert_eq!(rx.count(), MESSAGES / producers * producers);
+    let elapsed = start.elapsed();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    elapsed
+}
+
+fn lockfree_round(producers: usize) -> Duration {
+    let (tx, rx) = channels::lockfree::channel();
+    let start = Instant::now();
+    let handles: Vec<_> = (0..producers)
+        .map(|_| {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..MESSAGES / producers {
+                    tx.send(i).unwrap();
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+    assert_eq!(rx.count(), MESSAGES / producers * producers);
+    let elapsed = start.elapsed();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    elapsed
+}
+
+fn main() {
+    for &producers in &[1, 2, 4, 8] {
+        run("mutex", producers, mutex_round);
+        run("lockfree", producers, lockfree_round);
+    }
+}
@@ -0,0 +1,287 @@
+//! A channel where every receiver sees every message.
+//!
+//! Messages live in a ring buffer of fixed capacity and each receiver keeps its
+//! own cursor into it. Senders never block: once the buffer is full the oldest
+//! message is overwritten, and receivers that had not seen it yet get
+//! `Lagged` with the number of messages they missed.
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+
+use crate::SendError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    Lagged(u64),
+    Closed,
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvError::Lagged(n) => write!(f, "receiver lagged behind by {} messages", n),
+            RecvError::Closed => write!(f, "receiving on a closed channel"),
+        }
+    }
+}
+
+impl Error for RecvError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    Empty,
+    Lagged(u64),
+    Closed,
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => write!(f, "receiving on an empty channel"),
+            TryRecvError::Lagged(n) => write!(f, "receiver lagged behind by {} messages", n),
+            TryRecvError::Closed => write!(f, "receiving on a closed channel"),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
+
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        let mut inner = self.shared.lock();
+        inner.senders += 1;
+        Sender {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut inner = self.shared.lock();
+        inner.senders -= 1;
+        let was_last = inner.senders == 0;
+        drop(inner);
+        if was_last {
+            self.shared.available.notify_all();
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let mut inner = self.shared.lock();
+        if inner.receivers == 0 {
+            return Err(SendError(value));
+        }
+        if inner.slots.len() == inner.capacity {
+            inner.slots.pop_front();
+            inner.head += 1;
+        }
+        inner.slots.push_back(value);
+        drop(inner);
+        self.shared.available.notify_all();
+        Ok(())
+    }
+
+    // The new receiver only sees messages sent after this call.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let mut inner = self.shared.lock();
+        inner.receivers += 1;
+        Receiver {
+            shared: Arc::clone(&self.shared),
+            next: inner.tail(),
+        }
+    }
+}
+
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    next: u64,
+}
+
+impl<T: Clone> Receiver<T> {
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        let mut inner = self.shared.lock();
+        loop {
+            match inner.take(&mut self.next) {
+                Err(TryRecvError::Empty) => {
+                    inner = self
+                        .shared
+                        .available
+                        .wait(inner)
+                        .unwrap_or_else(PoisonError::into_inner)
+                }
+                Err(TryRecvError::Lagged(n)) => return Err(RecvError::Lagged(n)),
+                Err(TryRecvError::Closed) => return Err(RecvError::Closed),
+                Ok(t) => return Ok(t),
+            }
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.shared.lock().take(&mut self.next)
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        let mut inner = self.shared.lock();
+        inner.receivers += 1;
+        Receiver {
+            shared: Arc::clone(&self.shared),
+            next: self.next,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut inner = self.shared.lock();
+        inner.receivers -= 1;
+    }
+}
+
+struct Shared<T> {
+    inner: Mutex<Inner<T>>,
+    available: Condvar,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, Inner<T>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+struct Inner<T> {
+    slots: VecDeque<T>,
+    // sequence number of `slots[0]`
+    head: u64,
+    capacity: usize,
+    senders: usize,
+    receivers: usize,
+}
+
+impl<T> Inner<T> {
+    fn tail(&self) -> u64 {
+        self.head + self.slots.len() as u64
+    }
+
+    fn take(&self, next: &mut u64) -> Result<T, TryRecvError>
+    where
+        T: Clone,
+    {
+        if *next < self.head {
+            // skip to the oldest message still retained
+            let missed = self.head - *next;
+            *next = self.head;
+            return Err(TryRecvError::Lagged(missed));
+        }
+        if *next < self.tail() {
+            let t = self.slots[(*next - self.head) as usize].clone();
+            *next += 1;
+            return Ok(t);
+        }
+        if self.senders == 0 {
+            Err(TryRecvError::Closed)
+        } else {
+            Err(TryRecvError::Empty)
+        }
+    }
+}
+
+pub fn broadcast<T: Clone>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "broadcast capacity must be non-zero");
+    let shared = Arc::new(Shared {
+        inner: Mutex::new(Inner {
+            slots: VecDeque::with_capacity(capacity),
+            head: 0,
+            capacity,
+            senders: 1,
+            receivers: 1,
+        }),
+        available: Condvar::new(),
+    });
+
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared, next: 0 },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn every_receiver_sees_every_message() {
+        let (tx, mut rx1) = broadcast(4);
+        let mut rx2 = rx1.clone();
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        drop(tx);
+
+        for rx in [&mut rx1, &mut rx2].iter_mut() {
+            assert_eq!(rx.recv(), Ok(1));
+            assert_eq!(rx.recv(), Ok(2));
+            assert_eq!(rx.recv(), Err(RecvError::Closed));
+        }
+    }
+
+    #[test]
+    pub fn lagging_receiver_skips_ahead() {
+        let (tx, mut rx) = broadcast(2);
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Lagged(3)));
+        assert_eq!(rx.try_recv(), Ok(3));
+        assert_eq!(rx.try_recv(), Ok(4));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    pub fn subscribe_sees_only_later_messages() {
+        let (tx, rx) = broadcast(4);
+        tx.send(1).unwrap();
+        let mut late = tx.subscribe();
+        drop(rx);
+        tx.send(2).unwrap();
+
+        assert_eq!(late.try_recv(), Ok(2));
+        assert_eq!(late.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    pub fn blocked_receivers_wake_on_send() {
+        let (tx, rx) = broadcast(4);
+        let handles: Vec<_> = (0..3)
+            .map(|_| {
+                let mut rx = rx.clone();
+                std::thread::spawn(move || rx.recv())
+            })
+            .collect();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        tx.send("hello").unwrap();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), Ok("hello"));
+        }
+    }
+
+    #[test]
+    pub fn send_without_receivers_fails() {
+        let (tx, rx) = broadcast(1);
+        drop(rx);
+        assert_eq!(tx.send(1), Err(SendError(1)));
+    }
+}
@@ -1,12 +1,137 @@
+pub mod broadcast;
+pub mod lockfree;
+mod oneshot;
+mod queue;
+mod select;
+mod timer;
+pub mod watch;
+
+pub use crate::oneshot::{oneshot, OneshotReceiver, OneshotSender};
+pub use crate::select::Select;
+
+use crate::queue::{Queue, Schedule};
 use std::collections::VecDeque;
-use std::sync::{Arc, Condvar, Mutex};
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, Weak};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sending on a closed channel")
+    }
+}
+
+impl<T: fmt::Debug> Error for SendError<T> {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    Empty,
+    Disconnected,
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => write!(f, "receiving on an empty channel"),
+            TryRecvError::Disconnected => write!(f, "receiving on a closed channel"),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvTimeoutError {
+    Timeout,
+    Disconnected,
+}
+
+impl fmt::Display for RecvTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvTimeoutError::Timeout => write!(f, "timed out waiting on channel"),
+            RecvTimeoutError::Disconnected => write!(f, "receiving on a closed channel"),
+        }
+    }
+}
+
+impl Error for RecvTimeoutError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    Full(T),
+    Disconnected(T),
+}
+
+impl<T> TrySendError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            TrySendError::Full(t) | TrySendError::Disconnected(t) => t,
+        }
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrySendError::Full(_) => write!(f, "sending on a full channel"),
+            TrySendError::Disconnected(_) => write!(f, "sending on a closed channel"),
+        }
+    }
+}
+
+impl<T: fmt::Debug> Error for TrySendError<T> {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendTimeoutError<T> {
+    Timeout(T),
+    Disconnected(T),
+}
+
+impl<T> SendTimeoutError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            SendTimeoutError::Timeout(t) | SendTimeoutError::Disconnected(t) => t,
+        }
+    }
+}
+
+impl<T> fmt::Display for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendTimeoutError::Timeout(_) => write!(f, "timed out waiting on channel"),
+            SendTimeoutError::Disconnected(_) => write!(f, "sending on a closed channel"),
+        }
+    }
+}
+
+impl<T: fmt::Debug> Error for SendTimeoutError<T> {}
+
+// What `Sender::send` does when a bounded channel is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    #[default]
+    Block,
+    // evict the oldest queued value to make room
+    DropOldest,
+    // discard the value being sent
+    DropNewest,
+}
 
 pub struct Sender<T> {
     shared: Arc<Shared<T>>,
 }
 impl<T> Clone for Sender<T> {
     fn clone(&self) -> Self {
-        let mut inner = self.shared.inner.lock().unwrap();
+        let mut inner = self.shared.lock();
         inner.senders += 1;
         Sender {
             shared: Arc::clone(&self.shared),
@@ -16,114 +141,2110 @@ impl<T> Clone for Sender<T> {
 
 impl<T> Drop for Sender<T> {
     fn drop(&mut self) {
-        let mut inner = self.shared.inner.lock().unwrap();
+        let mut inner = self.shared.lock();
         inner.senders -= 1;
         let was_last = inner.senders == 0;
+        let wakers = if was_last {
+            inner.take_wakers()
+        } else {
+            Vec::new()
+        };
         drop(inner);
         if was_last {
-            self.shared.available.notify_one();
+            self.shared.notify_receivers(wakers, true);
         }
     }
 }
 
 impl<T> Sender<T> {
-    pub fn send(&mut self, value: T) {
-        let mut inner = self.shared.inner.lock().unwrap();
-        inner.queue.push_back(value);
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        self.send_with_priority(value, 0)
+    }
+
+    // Higher priorities are received first on a `priority_channel`; other
+    // channels ignore the priority.
+    pub fn send_with_priority(&self, value: T, priority: u32) -> Result<(), SendError<T>> {
+        self.send_until(Message::new(value), priority, None)
+            .map_err(|e| SendError(e.into_inner()))
+    }
+
+    // Receivers silently discard the value if it is still queued once `ttl`
+    // has passed.
+    pub fn send_with_ttl(&self, value: T, ttl: Duration) -> Result<(), SendError<T>> {
+        let message = Message {
+            value,
+            expires_at: Instant::now().checked_add(ttl),
+        };
+        self.send_until(message, 0, None)
+            .map_err(|e| SendError(e.into_inner()))
+    }
+
+    // Never blocks. On a rendezvous channel this only succeeds if a receiver
+    // is already blocked in `recv`; async and `Select` waiters may move on
+    // without taking the value.
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let mut inner = self.shared.lock();
+        if inner.closed {
+            return Err(TrySendError::Disconnected(value));
+        }
+        if inner.capacity == Some(0) && inner.waiting == 0 {
+            return Err(TrySendError::Full(value));
+        }
+        let mut discarded = None;
+        if inner.is_full() {
+            match inner.overflow {
+                OverflowPolicy::Block => return Err(TrySendError::Full(value)),
+                OverflowPolicy::DropOldest => discarded = inner.evict_oldest(),
+                OverflowPolicy::DropNewest => {
+                    inner.dropped += 1;
+                    return Ok(());
+                }
+            }
+        }
+        inner.queue.push(Message::new(value), 0);
+        let wakers = inner.take_wakers();
+        drop(inner);
+        self.shared.notify_receivers(wakers, false);
+        drop(discarded);
+        Ok(())
+    }
+
+    // The value stays invisible to receivers until `at`. Never blocks: on a
+    // bounded channel a due value waits until the channel has room.
+    pub fn send_at(&self, value: T, at: Instant) -> Result<(), SendError<T>> {
+        let mut inner = self.shared.lock();
+        if inner.closed {
+            return Err(SendError(value));
+        }
+        let earliest = inner.next_due().is_none_or(|due| at < due);
+        inner.delayed.push(at, Message::new(value));
+        // blocked receivers are sleeping until the previous earliest due time
+        let wakers = if earliest {
+            inner.take_wakers()
+        } else {
+            Vec::new()
+        };
+        drop(inner);
+        if earliest {
+            self.shared.notify_receivers(wakers, true);
+        }
+        Ok(())
+    }
+
+    pub fn send_after(&self, value: T, delay: Duration) -> Result<(), SendError<T>> {
+        match Instant::now().checked_add(delay) {
+            Some(at) => self.send_at(value, at),
+            // never due, so no receiver would ever see it
+            None => Ok(()),
+        }
+    }
+
+    pub fn send_timeout(&self, value: T, timeout: Duration) -> Result<(), SendTimeoutError<T>> {
+        self.send_until(Message::new(value), 0, Instant::now().checked_add(timeout))
+    }
+
+    fn send_until(
+        &self,
+        message: Message<T>,
+        priority: u32,
+        deadline: Option<Instant>,
+    ) -> Result<(), SendTimeoutError<T>> {
+        let mut discarded = None;
+        let mut inner = self.shared.lock();
+        loop {
+            if inner.closed {
+                return Err(SendTimeoutError::Disconnected(message.value));
+            }
+            if !inner.is_full() {
+                break;
+            }
+            match inner.overflow {
+                OverflowPolicy::Block => {}
+                OverflowPolicy::DropOldest => {
+                    discarded = inner.evict_oldest();
+                    break;
+                }
+                OverflowPolicy::DropNewest => {
+                    inner.dropped += 1;
+                    drop(inner);
+                    return Ok(());
+                }
+            }
+            inner = match self.shared.wait_until(&self.shared.space, inner, deadline) {
+                Ok(inner) => inner,
+                Err(_) => return Err(SendTimeoutError::Timeout(message.value)),
+            };
+        }
+        inner.queue.push(message, priority);
+        let ticket = inner.taken + 1;
+        let rendezvous = inner.capacity == Some(0);
+        if rendezvous {
+            inner.handoff = Some(ticket);
+        }
+        let wakers = inner.take_wakers();
+        drop(inner);
+        self.shared.notify_receivers(wakers, false);
+        drop(discarded);
+        if !rendezvous {
+            return Ok(());
+        }
+        let mut inner = self.shared.lock();
+        while inner.taken < ticket {
+            if inner.closed {
+                inner.end_handoff(ticket);
+                return match inner.take_back() {
+                    Some(value) => Err(SendTimeoutError::Disconnected(value)),
+                    None => Ok(()),
+                };
+            }
+            inner = match self.shared.wait_until(&self.shared.space, inner, deadline) {
+                Ok(inner) => inner,
+                Err(mut inner) => {
+                    inner.end_handoff(ticket);
+                    return match inner.take_back() {
+                        Some(value) => Err(SendTimeoutError::Timeout(value)),
+                        None => Ok(()),
+                    };
+                }
+            };
+        }
+        inner.end_handoff(ticket);
+        Ok(())
+    }
+
+    pub fn downgrade(&self) -> WeakSender<T> {
+        WeakSender {
+            shared: Arc::downgrade(&self.shared),
+        }
+    }
+
+    // Closes the channel for every sender at once; receivers still get what
+    // was already queued.
+    pub fn close_channel(&self) {
+        self.shared.close();
+    }
+
+    // Counts values queued in the channel, including those already moved
+    // into a receiver's local buffer.
+    pub fn len(&self) -> usize {
+        self.shared.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        self.shared.lock().capacity
+    }
+
+    pub fn sender_count(&self) -> usize {
+        self.shared.lock().senders
+    }
+
+    // Values discarded by the channel's overflow policy so far.
+    pub fn dropped_count(&self) -> usize {
+        self.shared.lock().dropped
+    }
+
+    // Values that outlived their TTL and were discarded by a receiver.
+    pub fn expired_count(&self) -> usize {
+        self.shared.expired.load(Ordering::Relaxed)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.shared.lock().is_disconnected()
+    }
+
+    // Queues every value under a single lock acquisition, only waiting when a
+    // bounded channel fills up. On disconnect, returns the values not sent.
+    pub fn send_all<I: IntoIterator<Item = T>>(&self, values: I) -> Result<(), SendError<Vec<T>>> {
+        // run the caller's iterator before taking the lock
+        let values: Vec<T> = values.into_iter().collect();
+        let mut values = values.into_iter().peekable();
+        let mut inner = self.shared.lock();
+        if inner.capacity == Some(0) {
+            drop(inner);
+            while let Some(value) = values.next() {
+                if let Err(SendError(value)) = self.send(value) {
+                    return Err(SendError(std::iter::once(value).chain(values).collect()));
+                }
+            }
+            return Ok(());
+        }
+        let mut discarded = Vec::new();
+        let mut pushed = false;
+        while values.peek().is_some() {
+            if inner.closed {
+                return Err(SendError(values.collect()));
+            }
+            if inner.is_full() {
+                match inner.overflow {
+                    OverflowPolicy::Block => {
+                        // let receivers drain what has been queued so far
+                        let wakers = inner.take_wakers();
+                        drop(inner);
+                        self.shared.notify_receivers(wakers, true);
+                        pushed = false;
+                        inner = self.shared.lock();
+                        while inner.is_full() && !inner.closed {
+                            inner = self
+                                .shared
+                                .space
+                                .wait(inner)
+                                .unwrap_or_else(PoisonError::into_inner);
+                        }
+                        continue;
+                    }
+                    OverflowPolicy::DropOldest => discarded.extend(inner.evict_oldest()),
+                    OverflowPolicy::DropNewest => {
+                        inner.dropped += 1;
+                        discarded.extend(values.next().map(Message::new));
+                        continue;
+                    }
+                }
+            }
+            if let Some(value) = values.next() {
+                inner.queue.push(Message::new(value), 0);
+            }
+            pushed = true;
+        }
+        let wakers = inner.take_wakers();
+        drop(inner);
+        if pushed {
+            self.shared.notify_receivers(wakers, true);
+        }
+        drop(discarded);
+        Ok(())
+    }
+}
+
+// Does not count as a sender, so it never keeps `recv` from returning `None`.
+pub struct WeakSender<T> {
+    shared: Weak<Shared<T>>,
+}
+
+impl<T> Clone for WeakSender<T> {
+    fn clone(&self) -> Self {
+        WeakSender {
+            shared: Weak::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> WeakSender<T> {
+    // Fails once every strong sender is gone or the channel is closed.
+    pub fn upgrade(&self) -> Option<Sender<T>> {
+        let shared = self.shared.upgrade()?;
+        let mut inner = shared.lock();
+        if inner.is_disconnected() {
+            return None;
+        }
+        inner.senders += 1;
         drop(inner);
-        self.shared.available.notify_one();
+        Some(Sender { shared })
     }
 }
 
 pub struct Receiver<T> {
     shared: Arc<Shared<T>>,
-    buffer: VecDeque<T>,
+    // only `clone` and `Select` reach this through `&self`; everything else
+    // goes through `local` without locking
+    buffer: Mutex<VecDeque<Message<T>>>,
+}
+
+fn local<T>(buffer: &mut Mutex<VecDeque<Message<T>>>) -> &mut VecDeque<Message<T>> {
+    buffer.get_mut().unwrap_or_else(PoisonError::into_inner)
 }
 
 impl<T> Receiver<T> {
     pub fn recv(&mut self) -> Option<T> {
-        if let Some(t) = self.buffer.pop_front() {
-            return Some(t);
+        self.recv_until(None).ok()
+    }
+
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.recv_deadline(deadline),
+            None => self.recv().ok_or(RecvTimeoutError::Disconnected),
+        }
+    }
+
+    pub fn recv_deadline(&mut self, deadline: Instant) -> Result<T, RecvTimeoutError> {
+        self.recv_until(Some(deadline))
+    }
+
+    fn recv_until(&mut self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        if let Some(t) = self.pop_buffered() {
+            return Ok(t);
         }
-        let mut inner = self.shared.inner.lock().unwrap();
+        let mut inner = self.shared.lock();
         loop {
-            match inner.queue.pop_front() {
+            match self.shared.pop_live(&mut inner) {
                 Some(t) => {
-                    if !inner.queue.is_empty() {
-                        std::mem::swap(&mut inner.queue, &mut self.buffer);
-                    }
-                    return Some(t);
+                    self.shared.finish_recv(inner, local(&mut self.buffer), 1);
+                    return Ok(t);
+                }
+                None if inner.is_finished() => return Err(RecvTimeoutError::Disconnected),
+                None => {
+                    inner = self
+                        .shared
+                        .wait_for_value(inner, deadline)
+                        .map_err(|_| RecvTimeoutError::Timeout)?
+                }
+            }
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        if let Some(t) = self.pop_buffered() {
+            return Ok(t);
+        }
+        let mut inner = self.shared.lock();
+        match self.shared.pop_live(&mut inner) {
+            Some(t) => {
+                self.shared.finish_recv(inner, local(&mut self.buffer), 1);
+                Ok(t)
+            }
+            None if inner.is_finished() => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    // Stops accepting new values while letting this and any other receiver
+    // drain what is already queued.
+    pub fn close(&self) {
+        self.shared.close();
+    }
+
+    // Blocks until at least one value is available, then moves up to `max`
+    // values into `out`. Returns 0 once the channel is disconnected.
+    pub fn recv_many(&mut self, out: &mut Vec<T>, max: usize) -> usize {
+        if max == 0 {
+            return 0;
+        }
+        let mut n = 0;
+        while n < max {
+            match self.pop_buffered() {
+                Some(t) => out.push(t),
+                None => break,
+            }
+            n += 1;
+        }
+        if n > 0 {
+            return n;
+        }
+        let mut inner = self.shared.lock();
+        loop {
+            while n < max {
+                match self.shared.pop_live(&mut inner) {
+                    Some(t) => out.push(t),
+                    None => break,
                 }
-                None if inner.senders == 0 => return None,
-                None => inner = self.shared.available.wait(inner).unwrap(),
+                n += 1;
+            }
+            if n > 0 {
+                self.shared.finish_recv(inner, local(&mut self.buffer), n);
+                return n;
+            }
+            if inner.is_finished() {
+                return 0;
+            }
+            inner = match self.shared.wait_for_value(inner, None) {
+                Ok(inner) | Err(inner) => inner,
+            };
+        }
+    }
+
+    // Takes everything queued right now without blocking.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        let buffer = local(&mut self.buffer);
+        self.shared
+            .buffered
+            .fetch_sub(buffer.len(), Ordering::Relaxed);
+        let mut inner = self.shared.lock();
+        inner.promote_due();
+        let moved = inner.queue.len();
+        if moved > 0 {
+            match inner.queue.as_fifo_mut() {
+                Some(queue) if buffer.is_empty() => std::mem::swap(queue, buffer),
+                Some(queue) => buffer.append(queue),
+                None => inner.queue.pop_into(buffer, moved),
+            }
+            inner.taken += moved as u64;
+            self.shared.finish_recv(inner, buffer, moved);
+        }
+        let before = buffer.len();
+        buffer.retain(|message| !message.is_expired());
+        self.shared
+            .expired
+            .fetch_add(before - buffer.len(), Ordering::Relaxed);
+        buffer.drain(..).map(|message| message.value)
+    }
+
+    fn pop_buffered(&mut self) -> Option<T> {
+        while let Some(message) = local(&mut self.buffer).pop_front() {
+            self.shared.buffered.fetch_sub(1, Ordering::Relaxed);
+            if message.is_expired() {
+                self.shared.expired.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+            return Some(message.value);
+        }
+        None
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        self.shared.lock().capacity
+    }
+
+    // Counts `Sender` handles only; `tick` and `after` channels have none.
+    pub fn sender_count(&self) -> usize {
+        self.shared.lock().senders
+    }
+
+    // Values discarded by the channel's overflow policy so far.
+    pub fn dropped_count(&self) -> usize {
+        self.shared.lock().dropped
+    }
+
+    // Values that outlived their TTL and were discarded by a receiver.
+    pub fn expired_count(&self) -> usize {
+        self.shared.expired.load(Ordering::Relaxed)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        // pending delayed values and ticks still arrive without a sender
+        self.shared.lock().is_finished()
+    }
+
+    pub fn recv_async(&mut self) -> RecvFuture<'_, T> {
+        RecvFuture {
+            receiver: self,
+            waker: None,
+        }
+    }
+
+    // Same contract as `futures::Stream::poll_next`.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        if let Some(t) = self.pop_buffered() {
+            return Poll::Ready(Some(t));
+        }
+        let mut inner = self.shared.lock();
+        match self.shared.pop_live(&mut inner) {
+            Some(t) => {
+                self.shared.finish_recv(inner, local(&mut self.buffer), 1);
+                Poll::Ready(Some(t))
+            }
+            None if inner.is_finished() => Poll::Ready(None),
+            None => {
+                self.shared.register_waker(&mut inner, cx.waker());
+                Poll::Pending
             }
         }
     }
 }
 
-impl<T> Iterator for Receiver<T> {
-    type Item = T;
+impl<T> select::Selectable for Receiver<T> {
+    fn poll_ready(&self, waker: Option<&Waker>) -> bool {
+        let buffer = self.buffer.lock().unwrap_or_else(PoisonError::into_inner);
+        if buffer.iter().any(|message| !message.is_expired()) {
+            return true;
+        }
+        drop(buffer);
+        let mut inner = self.shared.lock();
+        inner.promote_due();
+        self.shared.discard_expired(&mut inner);
+        if !inner.queue.is_empty() || inner.is_finished() {
+            return true;
+        }
+        if let Some(waker) = waker {
+            self.shared.register_waker(&mut inner, waker);
+        }
+        false
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.recv()
+    fn unregister(&self, waker: &Waker) {
+        self.shared.unregister_waker(&mut self.shared.lock(), waker);
     }
 }
 
-struct Shared<T> {
-    inner: Mutex<Inner<T>>,
-    available: Condvar,
+pub struct RecvFuture<'a, T> {
+    receiver: &'a mut Receiver<T>,
+    // registered with the channel while pending
+    waker: Option<Waker>,
 }
 
-struct Inner<T> {
-    queue: VecDeque<T>,
-    senders: usize,
+impl<T> Future for RecvFuture<'_, T> {
+    type Output = Option<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let poll = self.receiver.poll_recv(cx);
+        self.waker = match poll {
+            Poll::Pending => Some(cx.waker().clone()),
+            Poll::Ready(_) => None,
+        };
+        poll
+    }
 }
 
-pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
-    let shared = Shared {
-        inner: Mutex::new(Inner {
-            queue: VecDeque::new(),
-            senders: 1,
-        }),
-        available: Condvar::new(),
-    };
-    let shared = Arc::new(shared);
+// A future dropped while pending is no longer a waiting receiver.
+impl<T> Drop for RecvFuture<'_, T> {
+    fn drop(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            let shared = &self.receiver.shared;
+            shared.unregister_waker(&mut shared.lock(), &waker);
+        }
+    }
+}
 
-    (
-        Sender {
-            shared: shared.clone(),
-        },
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        let mut inner = self.shared.lock();
+        inner.receivers += 1;
+        // values already moved into this receiver's buffer are shared work now
+        let mut buffer = self.buffer.lock().unwrap_or_else(PoisonError::into_inner);
+        self.shared.requeue(&mut inner, &mut buffer);
+        drop(buffer);
+        drop(inner);
         Receiver {
-            shared: shared.clone(),
-            buffer: VecDeque::new(),
-        },
-    )
+            shared: Arc::clone(&self.shared),
+            buffer: Mutex::new(VecDeque::new()),
+        }
+    }
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
-
-    #[test]
-    pub fn ping_pong() {
-        let (mut tx, mut rx) = channel();
-        tx.send(25);
-        assert_eq!(Some(25), rx.recv())
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let buffer = local(&mut self.buffer);
+        let mut inner = self.shared.lock();
+        inner.receivers -= 1;
+        let was_last = inner.receivers == 0;
+        let mut unreceived = None;
+        let mut undelivered = None;
+        let mut requeued = None;
+        if was_last {
+            self.shared
+                .buffered
+                .fetch_sub(buffer.len(), Ordering::Relaxed);
+            inner.closed = true;
+            // a pending rendezvous value is handed back by its waiting sender
+            if !inner.is_handing_off() {
+                unreceived = Some(inner.queue.take());
+            }
+            undelivered = Some(inner.delayed.take());
+            self.shared.alarm.clear();
+        } else if self.shared.requeue(&mut inner, buffer) {
+            // the remaining receivers pick up what this one had buffered
+            requeued = Some(inner.take_wakers());
+        }
+        drop(inner);
+        if was_last {
+            self.shared.space.notify_all();
+        }
+        if let Some(wakers) = requeued {
+            self.shared.notify_receivers(wakers, true);
+        }
+        // release the values outside the lock
+        drop(unreceived);
+        drop(undelivered);
     }
+}
 
-    #[test]
-    pub fn send_drop() {
-        let (tx, mut rx) = channel::<()>();
-        drop(tx);
+impl<T> Iterator for Receiver<T> {
+    type Item = T;
 
-        assert_eq!(rx.recv(), None)
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
     }
+}
 
-    // #[test]
-    // pub fn send_after_rx_close() {
-    // let (mut tx, rx) = channel();
-    // drop(rx);
-
-    // assert_eq!(tx.send(5), None)
-    // }
+struct Shared<T> {
+    inner: Mutex<Inner<T>>,
+    available: Condvar,
+    space: Condvar,
+    // values sitting in receivers' local buffers, which are drained without
+    // taking the lock
+    buffered: AtomicUsize,
+    expired: AtomicUsize,
+    alarm: Arc<timer::Alarm>,
+}
+
+impl<T> Shared<T> {
+    // Inner is never left half-updated, so a panic elsewhere while holding
+    // the lock must not take the channel down with it.
+    fn lock(&self) -> MutexGuard<'_, Inner<T>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Waits on `condvar` unless `deadline` has already passed, in which case
+    // the guard is handed back as an error. Callers re-check their condition
+    // on every return since wakeups may be spurious.
+    fn wait_until<'a>(
+        &self,
+        condvar: &Condvar,
+        inner: MutexGuard<'a, Inner<T>>,
+        deadline: Option<Instant>,
+    ) -> Result<MutexGuard<'a, Inner<T>>, MutexGuard<'a, Inner<T>>> {
+        match deadline {
+            None => Ok(condvar.wait(inner).unwrap_or_else(PoisonError::into_inner)),
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining == Duration::from_secs(0) {
+                    return Err(inner);
+                }
+                Ok(condvar
+                    .wait_timeout(inner, remaining)
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .0)
+            }
+        }
+    }
+
+    // Like `wait_until` on `available`, but lets `try_send` see that a
+    // receiver is blocked, and also returns once a delayed value comes due.
+    fn wait_for_value<'a>(
+        &self,
+        mut inner: MutexGuard<'a, Inner<T>>,
+        deadline: Option<Instant>,
+    ) -> Result<MutexGuard<'a, Inner<T>>, MutexGuard<'a, Inner<T>>> {
+        inner.waiting += 1;
+        let wake_at = match (deadline, inner.next_due()) {
+            (Some(deadline), Some(due)) => Some(deadline.min(due)),
+            (deadline, due) => deadline.or(due),
+        };
+        match self.wait_until(&self.available, inner, wake_at) {
+            Ok(mut inner) => {
+                inner.waiting -= 1;
+                Ok(inner)
+            }
+            Err(mut inner) if wake_at != deadline => {
+                inner.waiting -= 1;
+                Ok(inner)
+            }
+            Err(mut inner) => {
+                inner.waiting -= 1;
+                Err(inner)
+            }
+        }
+    }
+
+    // Pops the next value that has not outlived its TTL, discarding the rest.
+    fn pop_live(&self, inner: &mut Inner<T>) -> Option<T> {
+        let mut expired = 0;
+        let mut live = None;
+        while let Some(message) = inner.pop() {
+            if !message.is_expired() {
+                live = Some(message.value);
+                break;
+            }
+            expired += 1;
+        }
+        self.count_expired(inner, expired);
+        live
+    }
+
+    fn discard_expired(&self, inner: &mut Inner<T>) {
+        let before = inner.queue.len();
+        inner.queue.retain(|message| !message.is_expired());
+        let expired = before - inner.queue.len();
+        // a waiting rendezvous sender is done once its value is gone
+        inner.taken += expired as u64;
+        self.count_expired(inner, expired);
+    }
+
+    // Expired values leave room in a bounded channel for blocked senders.
+    fn count_expired(&self, inner: &Inner<T>, expired: usize) {
+        if expired == 0 {
+            return;
+        }
+        self.expired.fetch_add(expired, Ordering::Relaxed);
+        if inner.capacity.is_some() {
+            self.space.notify_all();
+        }
+    }
+
+    // Moves the live values in a receiver's local buffer back to the front of
+    // the queue. Returns whether any were moved.
+    fn requeue(&self, inner: &mut Inner<T>, buffer: &mut VecDeque<Message<T>>) -> bool {
+        self.buffered.fetch_sub(buffer.len(), Ordering::Relaxed);
+        let before = buffer.len();
+        buffer.retain(|message| !message.is_expired());
+        self.expired
+            .fetch_add(before - buffer.len(), Ordering::Relaxed);
+        if buffer.is_empty() {
+            return false;
+        }
+        match inner.queue.as_fifo_mut() {
+            Some(queue) => {
+                buffer.append(queue);
+                std::mem::swap(queue, buffer);
+            }
+            None => {
+                for message in buffer.drain(..) {
+                    inner.queue.push(message, 0);
+                }
+            }
+        }
+        true
+    }
+
+    fn len(&self) -> usize {
+        let inner = self.lock();
+        inner.queue.len() + self.buffered.load(Ordering::Relaxed)
+    }
+
+    fn close(&self) {
+        let mut inner = self.lock();
+        inner.closed = true;
+        inner.ticker = None;
+        let wakers = inner.take_wakers();
+        drop(inner);
+        self.notify_receivers(wakers, true);
+        self.space.notify_all();
+    }
+
+    fn register_waker(&self, inner: &mut Inner<T>, waker: &Waker) {
+        inner.register_waker(waker);
+        // no sender will wake it when a delayed value comes due
+        if let Some(due) = inner.next_due() {
+            self.alarm.wake_at(due, waker);
+        }
+    }
+
+    fn unregister_waker(&self, inner: &mut Inner<T>, waker: &Waker) {
+        inner.wakers.retain(|w| !w.will_wake(waker));
+        self.alarm.cancel(waker);
+    }
+
+    fn notify_receivers(&self, wakers: Vec<Waker>, all: bool) {
+        if all {
+            self.available.notify_all();
+        } else {
+            self.available.notify_one();
+        }
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    fn finish_recv(
+        &self,
+        mut inner: MutexGuard<'_, Inner<T>>,
+        buffer: &mut VecDeque<Message<T>>,
+        received: usize,
+    ) {
+        let receivers = inner.receivers;
+        let capacity = inner.capacity;
+        // with several receivers, leave the rest of the queue for the others;
+        // on a bounded channel buffered values would no longer count towards
+        // the capacity
+        if let (Some(queue), None) = (inner.queue.as_fifo_mut(), capacity) {
+            if receivers == 1 && !queue.is_empty() {
+                std::mem::swap(queue, buffer);
+                self.buffered.fetch_add(buffer.len(), Ordering::Relaxed);
+            }
+        }
+        drop(inner);
+        if let Some(cap) = capacity {
+            if cap == 0 || received > 1 {
+                self.space.notify_all();
+            } else {
+                self.space.notify_one();
+            }
+        }
+    }
+}
+
+struct Message<T> {
+    value: T,
+    expires_at: Option<Instant>,
+}
+
+impl<T> Message<T> {
+    fn new(value: T) -> Self {
+        Message {
+            value,
+            expires_at: None,
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        match self.expires_at {
+            Some(at) => at <= Instant::now(),
+            None => false,
+        }
+    }
+}
+
+struct Inner<T> {
+    queue: Queue<Message<T>>,
+    // values sent with `send_at` that are not due yet
+    delayed: Schedule<Message<T>>,
+    ticker: Option<Ticker<T>>,
+    senders: usize,
+    receivers: usize,
+    closed: bool,
+    capacity: Option<usize>,
+    overflow: OverflowPolicy,
+    dropped: usize,
+    taken: u64,
+    // ticket of the rendezvous sender waiting for its queued value to be taken
+    handoff: Option<u64>,
+    // receivers blocked waiting for a value
+    waiting: usize,
+    wakers: Vec<Waker>,
+}
+
+// Produces a value every `period` until the channel is closed.
+struct Ticker<T> {
+    period: Duration,
+    // `None` once the next tick lies beyond what `Instant` can represent
+    next: Option<Instant>,
+    make: fn(Instant) -> T,
+}
+
+impl<T> Ticker<T> {
+    // Ticks missed by a slow receiver are skipped rather than queued up.
+    fn advance(&mut self, now: Instant) {
+        self.next = self.next.and_then(|next| next.checked_add(self.period));
+        if let Some(next) = self.next.filter(|&next| next <= now) {
+            let behind = (now - next).as_nanos() / self.period.as_nanos();
+            let skipped = (behind + 1).min(u32::MAX as u128) as u32;
+            self.next = self
+                .period
+                .checked_mul(skipped)
+                .and_then(|skip| next.checked_add(skip));
+        }
+    }
+}
+
+impl<T> Inner<T> {
+    // Bounded channels never move values into a receiver's buffer, so the
+    // front of the queue is the oldest value not yet received.
+    fn evict_oldest(&mut self) -> Option<Message<T>> {
+        self.dropped += 1;
+        self.queue.pop()
+    }
+
+    fn is_full(&self) -> bool {
+        match self.capacity {
+            // a rendezvous channel holds at most the one value being handed off
+            Some(cap) => self.queue.len() >= cap.max(1),
+            None => false,
+        }
+    }
+
+    fn next_due(&self) -> Option<Instant> {
+        let tick = self.ticker.as_ref().and_then(|ticker| ticker.next);
+        match (self.delayed.next_due(), tick) {
+            (Some(due), Some(tick)) => Some(due.min(tick)),
+            (due, tick) => due.or(tick),
+        }
+    }
+
+    // Moves delayed values that are due onto the queue, as far as capacity
+    // allows.
+    fn promote_due(&mut self) {
+        if self.delayed.is_empty() && self.ticker.is_none() {
+            return;
+        }
+        let now = Instant::now();
+        if let Some(ticker) = self.ticker.as_mut() {
+            if let Some(next) = ticker.next.filter(|&next| next <= now) {
+                self.queue.push(Message::new((ticker.make)(next)), 0);
+                ticker.advance(now);
+            }
+        }
+        // a waiting rendezvous sender expects its value to be the only one
+        // queued, which `is_full` already ensures
+        while !self.is_full() {
+            match self.delayed.pop_due(now) {
+                Some(message) => self.queue.push(message, 0),
+                None => break,
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Option<Message<T>> {
+        self.promote_due();
+        let message = self.queue.pop()?;
+        self.taken += 1;
+        // a due value claims the freed slot ahead of blocked senders
+        self.promote_due();
+        Some(message)
+    }
+
+    // Reclaims a rendezvous value nobody took; while its sender waits it is
+    // the only value queued.
+    fn take_back(&mut self) -> Option<T> {
+        self.queue.pop_back().map(|message| message.value)
+    }
+
+    fn is_handing_off(&self) -> bool {
+        self.handoff.is_some_and(|ticket| self.taken < ticket)
+    }
+
+    // Another sender may have queued its own value since `ticket` was taken.
+    fn end_handoff(&mut self, ticket: u64) {
+        if self.handoff == Some(ticket) {
+            self.handoff = None;
+        }
+    }
+
+    fn register_waker(&mut self, waker: &Waker) {
+        if !self.wakers.iter().any(|w| w.will_wake(waker)) {
+            self.wakers.push(waker.clone());
+        }
+    }
+
+    // No more values will be sent.
+    fn is_disconnected(&self) -> bool {
+        self.senders == 0 || self.closed
+    }
+
+    // No more values will arrive once the queue is empty.
+    fn is_finished(&self) -> bool {
+        self.is_disconnected() && self.delayed.is_empty() && self.ticker.is_none()
+    }
+
+    fn take_wakers(&mut self) -> Vec<Waker> {
+        std::mem::take(&mut self.wakers)
+    }
+}
+
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    new_channel(Queue::fifo(None), None, OverflowPolicy::Block)
+}
+
+pub fn sync_channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    sync_channel_with_overflow(capacity, OverflowPolicy::Block)
+}
+
+pub fn sync_channel_with_overflow<T>(
+    capacity: usize,
+    overflow: OverflowPolicy,
+) -> (Sender<T>, Receiver<T>) {
+    assert!(
+        capacity > 0 || overflow == OverflowPolicy::Block,
+        "a rendezvous channel cannot drop values"
+    );
+    new_channel(Queue::fifo(Some(capacity)), Some(capacity), overflow)
+}
+
+pub fn priority_channel<T>() -> (Sender<T>, Receiver<T>) {
+    new_channel(Queue::priority(), None, OverflowPolicy::Block)
+}
+
+// Delivers the instant it fired once, after `delay`, then disconnects.
+pub fn after(delay: Duration) -> Receiver<Instant> {
+    let (tx, rx) = channel();
+    if let Some(at) = Instant::now().checked_add(delay) {
+        let _ = tx.send_at(at, at);
+    }
+    rx
+}
+
+// Delivers the instant of each tick every `period`, starting one period from
+// now. Ticks are produced lazily by whoever receives, so no thread is needed.
+pub fn tick(period: Duration) -> Receiver<Instant> {
+    assert!(
+        period > Duration::from_secs(0),
+        "tick period must be non-zero"
+    );
+    let (tx, rx) = channel();
+    tx.shared.lock().ticker = Some(Ticker {
+        period,
+        next: Instant::now().checked_add(period),
+        make: |at| at,
+    });
+    rx
+}
+
+fn new_channel<T>(
+    queue: Queue<Message<T>>,
+    capacity: Option<usize>,
+    overflow: OverflowPolicy,
+) -> (Sender<T>, Receiver<T>) {
+    let shared = Shared {
+        inner: Mutex::new(Inner {
+            queue,
+            delayed: Schedule::new(),
+            ticker: None,
+            senders: 1,
+            receivers: 1,
+            closed: false,
+            capacity,
+            overflow,
+            dropped: 0,
+            taken: 0,
+            handoff: None,
+            waiting: 0,
+            wakers: Vec::new(),
+        }),
+        available: Condvar::new(),
+        space: Condvar::new(),
+        buffered: AtomicUsize::new(0),
+        expired: AtomicUsize::new(0),
+        alarm: Arc::default(),
+    };
+    let shared = Arc::new(shared);
+
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver {
+            shared: shared.clone(),
+            buffer: Mutex::new(VecDeque::new()),
+        },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn ping_pong() {
+        let (tx, mut rx) = channel();
+        tx.send(25).unwrap();
+        assert_eq!(Some(25), rx.recv())
+    }
+
+    #[test]
+    pub fn send_drop() {
+        let (tx, mut rx) = channel::<()>();
+        drop(tx);
+
+        assert_eq!(rx.recv(), None)
+    }
+
+    #[test]
+    pub fn send_after_rx_close() {
+        let (tx, rx) = channel();
+        drop(rx);
+
+        assert_eq!(tx.send(5), Err(SendError(5)))
+    }
+
+    #[test]
+    pub fn sync_send_blocks_when_full() {
+        let (tx, mut rx) = sync_channel(1);
+        tx.send(1).unwrap();
+
+        let handle = std::thread::spawn(move || {
+            tx.send(2).unwrap();
+        });
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!handle.is_finished());
+
+        assert_eq!(rx.recv(), Some(1));
+        handle.join().unwrap();
+        assert_eq!(rx.recv(), Some(2));
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    pub fn sync_capacity_holds_after_recv() {
+        let (tx, mut rx) = sync_channel(2);
+        tx.send(0).unwrap();
+        tx.send(1).unwrap();
+        assert_eq!(rx.try_recv(), Ok(0));
+
+        assert_eq!(tx.try_send(2), Ok(()));
+        assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+        assert_eq!(rx.len(), 2);
+        assert_eq!(rx.drain().collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    pub fn sync_send_unblocks_on_rx_close() {
+        let (tx, rx) = sync_channel(1);
+        tx.send(1).unwrap();
+
+        let handle = std::thread::spawn(move || tx.send(2));
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        drop(rx);
+
+        assert_eq!(handle.join().unwrap(), Err(SendError(2)));
+    }
+
+    #[test]
+    pub fn rendezvous_send_waits_for_recv() {
+        let (tx, mut rx) = sync_channel(0);
+
+        let handle = std::thread::spawn(move || {
+            tx.send(1).unwrap();
+            tx.send(2).unwrap();
+        });
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!handle.is_finished());
+
+        assert_eq!(rx.recv(), Some(1));
+        assert_eq!(rx.recv(), Some(2));
+        handle.join().unwrap();
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    pub fn rendezvous_send_returns_value_on_rx_close() {
+        let (tx, rx) = sync_channel(0);
+
+        let handle = std::thread::spawn(move || tx.send(7));
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        drop(rx);
+
+        assert_eq!(handle.join().unwrap(), Err(SendError(7)));
+    }
+
+    #[test]
+    pub fn try_recv_empty_then_disconnected() {
+        let (tx, mut rx) = channel();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        drop(tx);
+
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    pub fn recv_timeout_times_out_then_receives() {
+        let (tx, mut rx) = channel();
+        let timeout = Duration::from_millis(50);
+
+        let start = Instant::now();
+        assert_eq!(rx.recv_timeout(timeout), Err(RecvTimeoutError::Timeout));
+        assert!(start.elapsed() >= timeout);
+
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            tx.send(3).unwrap();
+        });
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(3));
+        handle.join().unwrap();
+        assert_eq!(
+            rx.recv_deadline(Instant::now() + timeout),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[test]
+    pub fn cloned_receivers_share_queue() {
+        let (tx, mut rx1) = channel();
+        let mut rx2 = rx1.clone();
+        for i in 0..3 {
+            tx.send(i).unwrap();
+        }
+
+        assert_eq!(rx1.recv(), Some(0));
+        assert_eq!(rx2.try_recv(), Ok(1));
+        assert_eq!(rx1.try_recv(), Ok(2));
+    }
+
+    #[test]
+    pub fn dropped_receiver_hands_back_buffered_values() {
+        let (tx, mut rx1) = channel();
+        for i in 0..3 {
+            tx.send(i).unwrap();
+        }
+        // 1 and 2 move into rx1's buffer
+        assert_eq!(rx1.recv(), Some(0));
+        let rx2 = rx1.clone();
+        drop(rx1);
+        drop(tx);
+
+        assert_eq!(rx2.collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    pub fn clone_sees_values_buffered_before_it() {
+        let (tx, mut rx1) = channel();
+        for i in 0..3 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx1.recv(), Some(0));
+        let mut rx2 = rx1.clone();
+
+        assert_eq!(rx2.try_recv(), Ok(1));
+        assert_eq!(rx1.try_recv(), Ok(2));
+        assert_eq!(rx2.len(), 0);
+    }
+
+    #[test]
+    pub fn cloned_receivers_each_value_once() {
+        let (tx, rx) = channel();
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let rx = rx.clone();
+                std::thread::spawn(move || rx.collect::<Vec<usize>>())
+            })
+            .collect();
+        drop(rx);
+
+        let producers: Vec<_> = (0..4)
+            .map(|p| {
+                let tx = tx.clone();
+                std::thread::spawn(move || {
+                    for i in 0..250 {
+                        tx.send(p * 250 + i).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+        for producer in producers {
+            producer.join().unwrap();
+        }
+
+        let mut all: Vec<usize> = handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect();
+        all.sort_unstable();
+        assert_eq!(all, (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn send_fails_only_after_last_receiver_drops() {
+        let (tx, rx1) = channel();
+        let rx2 = rx1.clone();
+        drop(rx1);
+        assert_eq!(tx.send(1), Ok(()));
+        drop(rx2);
+        assert_eq!(tx.send(2), Err(SendError(2)));
+    }
+
+    fn poison<T: Send + 'static>(shared: &Arc<Shared<T>>) {
+        let other = Arc::clone(shared);
+        std::thread::spawn(move || {
+            let _inner = other.inner.lock().unwrap();
+            panic!("poisoning the channel");
+        })
+        .join()
+        .unwrap_err();
+        assert!(shared.inner.is_poisoned());
+    }
+
+    #[test]
+    pub fn survives_poisoned_lock() {
+        let (tx, mut rx) = channel();
+        poison(&tx.shared);
+
+        tx.send(1).unwrap();
+        let tx2 = tx.clone();
+        tx2.send(2).unwrap();
+        drop(tx);
+        drop(tx2);
+
+        assert_eq!(rx.recv(), Some(1));
+        assert_eq!(rx.recv(), Some(2));
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    pub fn blocked_recv_survives_poisoned_lock() {
+        let (tx, mut rx) = channel();
+        let handle = std::thread::spawn(move || rx.recv());
+        std::thread::sleep(Duration::from_millis(50));
+
+        poison(&tx.shared);
+        tx.send(4).unwrap();
+
+        assert_eq!(handle.join().unwrap(), Some(4));
+    }
+
+    struct ThreadWaker(std::thread::Thread);
+
+    impl std::task::Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    pub fn recv_async_from_blocking_sender() {
+        let (tx, mut rx) = channel();
+        let handle = std::thread::spawn(move || {
+            for i in 0..3 {
+                std::thread::sleep(Duration::from_millis(10));
+                tx.send(i).unwrap();
+            }
+        });
+
+        let received = block_on(async {
+            let mut received = Vec::new();
+            while let Some(i) = rx.recv_async().await {
+                received.push(i);
+            }
+            received
+        });
+        handle.join().unwrap();
+        assert_eq!(received, vec![0, 1, 2]);
+    }
+
+    #[test]
+    pub fn poll_recv_registers_waker() {
+        struct CountingWaker(std::sync::atomic::AtomicUsize);
+
+        impl std::task::Wake for CountingWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+        }
+
+        let counter = Arc::new(CountingWaker(Default::default()));
+        let waker = Waker::from(Arc::clone(&counter));
+        let mut cx = Context::from_waker(&waker);
+        let (tx, mut rx) = channel();
+
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Pending);
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Pending);
+        tx.send(1).unwrap();
+        assert_eq!(counter.0.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(Some(1)));
+
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Pending);
+        drop(tx);
+        assert_eq!(counter.0.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(None));
+    }
+
+    #[test]
+    pub fn endpoints_are_send_and_sync() {
+        fn assert_send<T: Send>() {}
+        fn assert_sync<T: Sync>() {}
+
+        assert_send::<Sender<i32>>();
+        assert_sync::<Sender<i32>>();
+        assert_send::<Receiver<i32>>();
+        assert_sync::<Receiver<i32>>();
+        assert_send::<lockfree::Sender<i32>>();
+        assert_sync::<lockfree::Sender<i32>>();
+        assert_send::<lockfree::Receiver<i32>>();
+    }
+
+    #[test]
+    pub fn shared_sender_through_arc() {
+        let (tx, rx) = channel();
+        let tx = Arc::new(tx);
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let tx = Arc::clone(&tx);
+                std::thread::spawn(move || tx.send(i).unwrap())
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        drop(tx);
+
+        let mut received: Vec<_> = rx.collect();
+        received.sort_unstable();
+        assert_eq!(received, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    pub fn send_all_then_recv_many() {
+        let (tx, mut rx) = channel();
+        tx.send_all(0..10).unwrap();
+
+        let mut batch = Vec::new();
+        assert_eq!(rx.recv_many(&mut batch, 4), 4);
+        assert_eq!(batch, vec![0, 1, 2, 3]);
+        assert_eq!(rx.recv_many(&mut batch, 100), 6);
+        assert_eq!(batch, (0..10).collect::<Vec<_>>());
+
+        drop(tx);
+        assert_eq!(rx.recv_many(&mut batch, 100), 0);
+    }
+
+    #[test]
+    pub fn send_all_waits_for_space() {
+        let (tx, rx) = sync_channel(2);
+        let handle = std::thread::spawn(move || tx.send_all(0..100).unwrap());
+
+        assert_eq!(rx.collect::<Vec<_>>(), (0..100).collect::<Vec<_>>());
+        handle.join().unwrap();
+    }
+
+    #[test]
+    pub fn send_all_iterator_may_use_channel() {
+        let (tx, mut rx) = channel();
+        tx.send(0).unwrap();
+        tx.send_all((1..4).map(|i| i + tx.len())).unwrap();
+
+        assert_eq!(rx.drain().collect::<Vec<_>>(), vec![0, 2, 3, 4]);
+    }
+
+    #[test]
+    pub fn send_all_returns_unsent_values() {
+        let (tx, rx) = channel();
+        drop(rx);
+        assert_eq!(tx.send_all(vec![1, 2, 3]), Err(SendError(vec![1, 2, 3])));
+    }
+
+    #[test]
+    pub fn drain_takes_everything_queued() {
+        let (tx, mut rx) = channel();
+        tx.send_all(vec![1, 2, 3]).unwrap();
+        assert_eq!(rx.recv(), Some(1));
+        tx.send(4).unwrap();
+
+        assert_eq!(rx.drain().collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(rx.drain().count(), 0);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    pub fn priority_channel_orders_by_priority_then_fifo() {
+        let (tx, rx) = priority_channel();
+        tx.send_with_priority("bulk-1", 0).unwrap();
+        tx.send_with_priority("control-1", 10).unwrap();
+        tx.send("bulk-2").unwrap();
+        tx.send_with_priority("control-2", 10).unwrap();
+        tx.send_with_priority("warn", 5).unwrap();
+        drop(tx);
+
+        assert_eq!(
+            rx.collect::<Vec<_>>(),
+            vec!["control-1", "control-2", "warn", "bulk-1", "bulk-2"]
+        );
+    }
+
+    #[test]
+    pub fn priority_channel_overtakes_after_first_recv() {
+        let (tx, mut rx) = priority_channel();
+        tx.send_all(vec![1, 2, 3]).unwrap();
+        assert_eq!(rx.recv(), Some(1));
+
+        // nothing was hidden in the receiver's buffer, so this still overtakes
+        tx.send_with_priority(99, 1).unwrap();
+        assert_eq!(rx.recv(), Some(99));
+
+        let mut rest = Vec::new();
+        assert_eq!(rx.recv_many(&mut rest, 10), 2);
+        assert_eq!(rest, vec![2, 3]);
+    }
+
+    #[test]
+    pub fn fifo_channel_ignores_priority() {
+        let (tx, rx) = channel();
+        tx.send_with_priority(1, 0).unwrap();
+        tx.send_with_priority(2, 10).unwrap();
+        drop(tx);
+
+        assert_eq!(rx.collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    pub fn receiver_close_drains_queued_values() {
+        let (tx, mut rx) = channel();
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        rx.close();
+
+        assert_eq!(tx.send(3), Err(SendError(3)));
+        assert_eq!(rx.recv(), Some(1));
+        assert_eq!(rx.recv(), Some(2));
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    pub fn close_channel_ends_stream_for_all_senders() {
+        let (tx, rx) = channel();
+        let other = tx.clone();
+        let handle = std::thread::spawn(move || rx.collect::<Vec<_>>());
+
+        tx.send(1).unwrap();
+        other.send(2).unwrap();
+        tx.close_channel();
+        assert_eq!(other.send(3), Err(SendError(3)));
+
+        // both senders are still alive, yet the receiver sees the end
+        assert_eq!(handle.join().unwrap(), vec![1, 2]);
+        drop(other);
+    }
+
+    #[test]
+    pub fn close_wakes_blocked_sender() {
+        let (tx, rx) = sync_channel(1);
+        tx.send(1).unwrap();
+        let handle = std::thread::spawn(move || tx.send(2));
+        std::thread::sleep(Duration::from_millis(20));
+
+        rx.close();
+        assert_eq!(handle.join().unwrap(), Err(SendError(2)));
+    }
+
+    #[derive(Debug)]
+    struct DropCounter(Arc<std::sync::atomic::AtomicUsize>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    pub fn rx_drop_frees_queued_values() {
+        let dropped = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let count = || dropped.load(std::sync::atomic::Ordering::SeqCst);
+        let (tx, mut rx) = channel();
+        for _ in 0..3 {
+            tx.send(DropCounter(Arc::clone(&dropped))).unwrap();
+        }
+        // moves the other two into the receiver's buffer
+        drop(rx.recv());
+        assert_eq!(count(), 1);
+        for _ in 0..2 {
+            tx.send(DropCounter(Arc::clone(&dropped))).unwrap();
+        }
+
+        drop(rx);
+        assert_eq!(count(), 5);
+        drop(tx);
+    }
+
+    #[test]
+    pub fn rx_drop_frees_values_with_cloned_receivers() {
+        let dropped = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let count = || dropped.load(std::sync::atomic::Ordering::SeqCst);
+        let (tx, rx1) = priority_channel();
+        let rx2 = rx1.clone();
+        tx.send(DropCounter(Arc::clone(&dropped))).unwrap();
+
+        drop(rx1);
+        assert_eq!(count(), 0);
+        drop(rx2);
+        assert_eq!(count(), 1);
+        drop(tx);
+    }
+
+    #[test]
+    pub fn rx_drop_frees_values_and_wakes_blocked_sender() {
+        let dropped = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let (tx, rx) = sync_channel(2);
+        tx.send_all(vec![
+            DropCounter(Arc::clone(&dropped)),
+            DropCounter(Arc::clone(&dropped)),
+        ])
+        .unwrap();
+        let extra = DropCounter(Arc::clone(&dropped));
+        let handle = std::thread::spawn(move || tx.send(extra).is_err());
+        std::thread::sleep(Duration::from_millis(20));
+
+        drop(rx);
+        assert!(handle.join().unwrap());
+        assert_eq!(dropped.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    pub fn introspection_counts_buffered_values() {
+        let (tx, mut rx) = channel();
+        assert!(tx.is_empty());
+        assert_eq!(tx.capacity(), None);
+        tx.send_all(0..4).unwrap();
+        assert_eq!(rx.len(), 4);
+
+        // three values now live in the receiver's buffer
+        assert_eq!(rx.recv(), Some(0));
+        assert_eq!(tx.len(), 3);
+        tx.send(4).unwrap();
+        assert_eq!(rx.len(), 4);
+        assert_eq!(rx.drain().count(), 4);
+        assert!(rx.is_empty());
+    }
+
+    #[test]
+    pub fn introspection_tracks_senders_and_closed() {
+        let (tx, rx) = sync_channel::<()>(8);
+        assert_eq!(rx.capacity(), Some(8));
+        let tx2 = tx.clone();
+        assert_eq!(rx.sender_count(), 2);
+        assert!(!tx.is_closed());
+
+        drop(tx2);
+        assert_eq!(tx.sender_count(), 1);
+        drop(tx);
+        assert_eq!(rx.sender_count(), 0);
+        assert!(rx.is_closed());
+
+        let (tx, rx) = channel::<()>();
+        rx.close();
+        assert!(tx.is_closed());
+    }
+
+    #[test]
+    pub fn weak_sender_does_not_keep_channel_open() {
+        let (tx, mut rx) = channel();
+        let weak = tx.downgrade();
+        assert_eq!(rx.sender_count(), 1);
+
+        let upgraded = weak.upgrade().unwrap();
+        upgraded.send(1).unwrap();
+        drop(upgraded);
+        drop(tx);
+
+        assert_eq!(rx.recv(), Some(1));
+        assert_eq!(rx.recv(), None);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    pub fn weak_sender_fails_after_close_or_rx_drop() {
+        let (tx, rx) = channel::<()>();
+        let weak = tx.downgrade();
+        tx.close_channel();
+        assert!(weak.upgrade().is_none());
+
+        let (tx, rx2) = channel::<()>();
+        let weak = tx.clone().downgrade();
+        drop(rx2);
+        assert!(weak.upgrade().is_none());
+        drop((tx, rx));
+    }
+
+    #[test]
+    pub fn try_send_reports_full_and_disconnected() {
+        let (tx, mut rx) = sync_channel(1);
+        assert_eq!(tx.try_send(1), Ok(()));
+        assert_eq!(tx.try_send(2), Err(TrySendError::Full(2)));
+
+        assert_eq!(rx.recv(), Some(1));
+        assert_eq!(tx.try_send(3), Ok(()));
+        drop(rx);
+        assert_eq!(tx.try_send(4), Err(TrySendError::Disconnected(4)));
+    }
+
+    #[test]
+    pub fn try_send_rendezvous_needs_waiting_receiver() {
+        let (tx, mut rx) = sync_channel(0);
+        assert_eq!(tx.try_send(1), Err(TrySendError::Full(1)));
+
+        let handle = std::thread::spawn(move || rx.recv());
+        while tx.try_send(2).is_err() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(handle.join().unwrap(), Some(2));
+    }
+
+    #[test]
+    pub fn try_send_rendezvous_ignores_receivers_no_longer_waiting() {
+        let (tx, mut rx) = sync_channel(0);
+        let mut sel = Select::new();
+        sel.recv(&rx);
+        assert_eq!(sel.try_ready(), None);
+        drop(sel);
+        assert_eq!(tx.try_send(1), Err(TrySendError::Full(1)));
+
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut future = rx.recv_async();
+        assert_eq!(Pin::new(&mut future).poll(&mut cx), Poll::Pending);
+        // a pending future may still be dropped without taking the value
+        assert_eq!(tx.try_send(2), Err(TrySendError::Full(2)));
+        drop(future);
+        assert_eq!(tx.try_send(2), Err(TrySendError::Full(2)));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    pub fn rx_drop_frees_rendezvous_value_without_sender() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let (tx, rx) = sync_channel(0);
+        tx.send_after(DropCounter(drops.clone()), Duration::from_millis(1))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        // moves the due value onto the queue with no sender waiting on it
+        let mut sel = Select::new();
+        sel.recv(&rx);
+        assert_eq!(sel.try_ready(), Some(0));
+        drop(sel);
+
+        drop(rx);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    pub fn send_timeout_returns_value() {
+        let (tx, mut rx) = sync_channel(1);
+        tx.send(1).unwrap();
+        assert_eq!(
+            tx.send_timeout(2, Duration::from_millis(20)),
+            Err(SendTimeoutError::Timeout(2))
+        );
+
+        assert_eq!(rx.recv(), Some(1));
+        assert_eq!(tx.send_timeout(3, Duration::from_millis(20)), Ok(()));
+        rx.close();
+        assert_eq!(
+            tx.send_timeout(4, Duration::from_millis(20)),
+            Err(SendTimeoutError::Disconnected(4))
+        );
+    }
+
+    #[test]
+    pub fn send_timeout_rendezvous_takes_value_back() {
+        let (tx, mut rx) = sync_channel(0);
+        assert_eq!(
+            tx.send_timeout(1, Duration::from_millis(20)),
+            Err(SendTimeoutError::Timeout(1))
+        );
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    pub fn drop_oldest_evicts_front() {
+        let (tx, rx) = sync_channel_with_overflow(2, OverflowPolicy::DropOldest);
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(tx.try_send(5), Ok(()));
+        assert_eq!(tx.dropped_count(), 4);
+        drop(tx);
+
+        assert_eq!(rx.dropped_count(), 4);
+        assert_eq!(rx.collect::<Vec<_>>(), vec![4, 5]);
+    }
+
+    #[test]
+    pub fn drop_oldest_after_recv_evicts_oldest_pending() {
+        let (tx, mut rx) = sync_channel_with_overflow(2, OverflowPolicy::DropOldest);
+        tx.send(0).unwrap();
+        tx.send(1).unwrap();
+        assert_eq!(rx.recv(), Some(0));
+        for i in 2..5 {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+
+        assert_eq!(rx.dropped_count(), 2);
+        assert_eq!(rx.collect::<Vec<_>>(), vec![3, 4]);
+    }
+
+    #[test]
+    pub fn drop_newest_rejects_value() {
+        let (tx, rx) = sync_channel_with_overflow(2, OverflowPolicy::DropNewest);
+        tx.send_all(0..5).unwrap();
+        assert_eq!(tx.try_send(5), Ok(()));
+        assert_eq!(tx.dropped_count(), 4);
+        drop(tx);
+
+        assert_eq!(rx.collect::<Vec<_>>(), vec![0, 1]);
+    }
+
+    #[test]
+    pub fn drop_oldest_send_all_keeps_latest() {
+        let dropped = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let (tx, mut rx) = sync_channel_with_overflow(3, OverflowPolicy::DropOldest);
+        tx.send_all((0..10).map(|_| DropCounter(Arc::clone(&dropped))))
+            .unwrap();
+
+        assert_eq!(dropped.load(std::sync::atomic::Ordering::SeqCst), 7);
+        assert_eq!(rx.dropped_count(), 7);
+        assert_eq!(rx.drain().count(), 3);
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn rendezvous_rejects_dropping_policy() {
+        let _ = sync_channel_with_overflow::<()>(0, OverflowPolicy::DropNewest);
+    }
+
+    #[test]
+    pub fn expired_values_are_skipped() {
+        let (tx, mut rx) = channel();
+        tx.send_with_ttl(1, Duration::from_millis(10)).unwrap();
+        tx.send(2).unwrap();
+        tx.send_with_ttl(3, Duration::from_secs(60)).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert_eq!(rx.recv(), Some(2));
+        assert_eq!(rx.recv(), Some(3));
+        assert_eq!(rx.expired_count(), 1);
+        assert_eq!(tx.expired_count(), 1);
+    }
+
+    #[test]
+    pub fn expired_values_in_buffer_are_skipped() {
+        let (tx, mut rx) = channel();
+        tx.send(0).unwrap();
+        tx.send_with_ttl(1, Duration::from_millis(10)).unwrap();
+        tx.send_with_ttl(2, Duration::from_millis(10)).unwrap();
+        tx.send(3).unwrap();
+        // the rest of the queue moves into the receiver's buffer
+        assert_eq!(rx.recv(), Some(0));
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert_eq!(rx.try_recv(), Ok(3));
+        assert_eq!(rx.expired_count(), 2);
+        assert_eq!(rx.len(), 0);
+    }
+
+    #[test]
+    pub fn expired_values_make_room_for_blocked_sender() {
+        let (tx, mut rx) = sync_channel(1);
+        tx.send_with_ttl(1, Duration::from_millis(10)).unwrap();
+        let handle = std::thread::spawn(move || tx.send(2));
+        std::thread::sleep(Duration::from_millis(50));
+
+        assert_eq!(rx.recv_timeout(Duration::from_secs(2)), Ok(2));
+        assert_eq!(handle.join().unwrap(), Ok(()));
+        assert_eq!(rx.expired_count(), 1);
+    }
+
+    #[test]
+    pub fn expired_values_make_room_while_selecting() {
+        let (tx, mut rx) = sync_channel(1);
+        tx.send_with_ttl(1, Duration::from_millis(10)).unwrap();
+        let handle = std::thread::spawn(move || tx.send(2));
+        std::thread::sleep(Duration::from_millis(50));
+
+        let mut sel = Select::new();
+        sel.recv(&rx);
+        assert_eq!(sel.ready(), 0);
+        drop(sel);
+        assert_eq!(rx.recv(), Some(2));
+        assert_eq!(handle.join().unwrap(), Ok(()));
+    }
+
+    #[test]
+    pub fn expired_rendezvous_value_releases_sender() {
+        let (tx, rx) = sync_channel(0);
+        let handle = std::thread::spawn(move || tx.send_with_ttl(1, Duration::from_millis(10)));
+        std::thread::sleep(Duration::from_millis(50));
+
+        let mut sel = Select::new();
+        sel.recv(&rx);
+        assert_eq!(sel.try_ready(), None);
+        drop(sel);
+        drop(rx);
+
+        assert_eq!(handle.join().unwrap(), Ok(()));
+    }
+
+    #[test]
+    pub fn expired_values_skipped_by_batches() {
+        let (tx, mut rx) = channel();
+        tx.send_with_ttl(1, Duration::from_millis(10)).unwrap();
+        tx.send(2).unwrap();
+        tx.send_with_ttl(3, Duration::from_millis(10)).unwrap();
+        tx.send(4).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+
+        let mut batch = Vec::new();
+        assert_eq!(rx.recv_many(&mut batch, 1), 1);
+        assert_eq!(rx.drain().collect::<Vec<_>>(), vec![4]);
+        assert_eq!(batch, vec![2]);
+        assert_eq!(rx.expired_count(), 2);
+    }
+
+    #[test]
+    pub fn delayed_value_is_invisible_until_due() {
+        let (tx, mut rx) = channel();
+        let start = Instant::now();
+        tx.send_after(1, Duration::from_millis(50)).unwrap();
+        tx.send(2).unwrap();
+
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(rx.recv(), Some(1));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    pub fn delayed_values_arrive_in_due_order() {
+        let (tx, rx) = channel();
+        let now = Instant::now();
+        tx.send_at("late", now + Duration::from_millis(40)).unwrap();
+        tx.send_at("early", now + Duration::from_millis(20))
+            .unwrap();
+        tx.send_at("also late", now + Duration::from_millis(40))
+            .unwrap();
+        drop(tx);
+
+        // pending delayed values keep the channel open after the sender is gone
+        let received: Vec<_> = rx.collect();
+        assert_eq!(received, vec!["early", "late", "also late"]);
+    }
+
+    #[test]
+    pub fn earlier_delayed_value_wakes_blocked_receiver() {
+        let (tx, mut rx) = channel();
+        tx.send_after(1, Duration::from_secs(60)).unwrap();
+        let handle = std::thread::spawn(move || {
+            let start = Instant::now();
+            (rx.recv(), start.elapsed())
+        });
+        std::thread::sleep(Duration::from_millis(20));
+        tx.send_after(2, Duration::from_millis(20)).unwrap();
+
+        let (received, elapsed) = handle.join().unwrap();
+        assert_eq!(received, Some(2));
+        assert!(elapsed < Duration::from_secs(10));
+    }
+
+    #[test]
+    pub fn recv_timeout_before_delayed_value_is_due() {
+        let (tx, mut rx) = channel();
+        tx.send_after(1, Duration::from_millis(100)).unwrap();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(20)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        assert_eq!(rx.recv_timeout(Duration::from_secs(10)), Ok(1));
+    }
+
+    #[test]
+    pub fn recv_async_wakes_when_delayed_value_is_due() {
+        let (tx, mut rx) = channel();
+        tx.send_after(7, Duration::from_millis(20)).unwrap();
+        assert_eq!(block_on(rx.recv_async()), Some(7));
+    }
+
+    #[test]
+    pub fn delayed_values_respect_capacity() {
+        let (tx, mut rx) = sync_channel_with_overflow(2, OverflowPolicy::DropOldest);
+        for i in 0..4 {
+            tx.send_after(i, Duration::from_millis(1)).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(10));
+        tx.send(10).unwrap();
+
+        assert_eq!(rx.try_recv(), Ok(10));
+        assert_eq!(rx.len(), 2);
+        tx.send(11).unwrap();
+        assert_eq!(rx.len(), 2);
+        assert_eq!(rx.dropped_count(), 1);
+        drop(tx);
+        assert_eq!(rx.collect::<Vec<_>>(), vec![1, 11, 2, 3]);
+    }
+
+    #[test]
+    pub fn rx_drop_frees_delayed_values() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let (tx, rx) = channel();
+        tx.send_after(DropCounter(drops.clone()), Duration::from_secs(60))
+            .unwrap();
+        drop(rx);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    pub fn after_fires_once_then_disconnects() {
+        let start = Instant::now();
+        let mut rx = after(Duration::from_millis(20));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+        let fired = rx.recv().unwrap();
+        assert!(fired >= start + Duration::from_millis(20));
+        assert!(Instant::now() >= fired);
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    pub fn tick_fires_every_period() {
+        let start = Instant::now();
+        let ticks: Vec<_> = tick(Duration::from_millis(10)).take(3).collect();
+
+        // ticks stay on the original schedule even if one is skipped
+        for pair in ticks.windows(2) {
+            let gap = (pair[1] - pair[0]).as_nanos();
+            assert!(gap > 0);
+            assert_eq!(gap % Duration::from_millis(10).as_nanos(), 0);
+        }
+        assert!(ticks[0] >= start + Duration::from_millis(10));
+    }
+
+    #[test]
+    pub fn tick_skips_missed_ticks() {
+        let mut rx = tick(Duration::from_millis(10));
+        std::thread::sleep(Duration::from_millis(55));
+
+        let first = rx.try_recv().unwrap();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        let second = rx.recv().unwrap();
+        assert!(second - first >= Duration::from_millis(50));
+    }
+
+    #[test]
+    pub fn tick_in_select_with_data_channel() {
+        let (_tx, data) = channel::<i32>();
+        let mut ticker = tick(Duration::from_millis(10));
+
+        let mut sel = Select::new();
+        sel.recv(&data);
+        sel.recv(&ticker);
+        assert_eq!(sel.ready(), 1);
+        drop(sel);
+        assert!(ticker.try_recv().is_ok());
+    }
+
+    #[test]
+    pub fn close_stops_tick() {
+        let mut rx = tick(Duration::from_millis(5));
+        assert!(!rx.is_closed());
+        rx.close();
+
+        assert!(rx.is_closed());
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(100)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[test]
+    pub fn after_is_open_until_fired() {
+        let mut rx = after(Duration::from_millis(20));
+        assert!(!rx.is_closed());
+        assert_eq!(rx.sender_count(), 0);
+
+        assert!(rx.recv().is_some());
+        assert!(rx.is_closed());
+    }
+
+    #[test]
+    pub fn tick_with_huge_period_never_fires() {
+        let mut rx = tick(Duration::MAX);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(20)),
+            Err(RecvTimeoutError::Timeout)
+        );
+    }
+
+    #[test]
+    pub fn ticker_stops_when_next_tick_overflows() {
+        let now = Instant::now();
+        let mut ticker = Ticker {
+            period: Duration::MAX,
+            next: Some(now),
+            make: |at| at,
+        };
+        ticker.advance(now);
+        assert_eq!(ticker.next, None);
+    }
 }
@@ -0,0 +1,317 @@
+//! An MPSC channel backed by a lock-free linked list instead of `Mutex<Inner<T>>`.
+//!
+//! Producers only ever swap the list head, so sends never contend on a lock. The
+//! receiver parks only when it finds the queue empty.
+
+use std::cell::UnsafeCell;
+use std::ptr;
+use std::sync::atomic::{fence, AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, Thread};
+
+use crate::{SendError, TryRecvError};
+
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::Relaxed);
+        Sender {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shared.wake();
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        if self.shared.closed.load(Ordering::Acquire) {
+            return Err(SendError(value));
+        }
+        self.shared.queue.push(value);
+        self.shared.wake();
+        Ok(())
+    }
+}
+
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Receiver<T> {
+    pub fn recv(&mut self) -> Option<T> {
+        loop {
+            match self.try_recv() {
+                Ok(t) => return Some(t),
+                Err(TryRecvError::Disconnected) => return None,
+                Err(TryRecvError::Empty) => self.park(),
+            }
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        loop {
+            match self.pop() {
+                Pop::Data(t) => return Ok(t),
+                // a producer is between swapping the head and linking its node
+                Pop::Inconsistent => thread::yield_now(),
+                Pop::Empty => {
+                    if self.shared.senders.load(Ordering::Acquire) != 0 {
+                        return Err(TryRecvError::Empty);
+                    }
+                    // the last sender may have pushed right before dropping
+                    return match self.pop() {
+                        Pop::Data(t) => Ok(t),
+                        _ => Err(TryRecvError::Disconnected),
+                    };
+                }
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Pop<T> {
+        // SAFETY: `Receiver` is the only consumer and popping requires `&mut self`.
+        unsafe { self.shared.queue.pop() }
+    }
+
+    fn park(&mut self) {
+        let shared = &*self.shared;
+        *shared
+            .receiver
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(thread::current());
+        shared.parked.store(true, Ordering::SeqCst);
+        // pairs with the fence in `Shared::wake`: either we see the new head or
+        // the sender sees `parked` and unparks us
+        fence(Ordering::SeqCst);
+        if !shared.queue.is_empty() || shared.senders.load(Ordering::Acquire) == 0 {
+            shared.parked.store(false, Ordering::SeqCst);
+            return;
+        }
+        thread::park();
+        shared.parked.store(false, Ordering::SeqCst);
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.closed.store(true, Ordering::Release);
+    }
+}
+
+impl<T> Iterator for Receiver<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
+    }
+}
+
+struct Shared<T> {
+    queue: Queue<T>,
+    senders: AtomicUsize,
+    closed: AtomicBool,
+    parked: AtomicBool,
+    receiver: Mutex<Option<Thread>>,
+}
+
+impl<T> Shared<T> {
+    fn wake(&self) {
+        fence(Ordering::SeqCst);
+        if self.parked.swap(false, Ordering::SeqCst) {
+            let receiver = self.receiver.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(thread) = receiver.as_ref() {
+                thread.unpark();
+            }
+        }
+    }
+}
+
+struct Node<T> {
+    next: AtomicPtr<Node<T>>,
+    value: Option<T>,
+}
+
+impl<T> Node<T> {
+    fn alloc(value: Option<T>) -> *mut Node<T> {
+        Box::into_raw(Box::new(Node {
+            next: AtomicPtr::new(ptr::null_mut()),
+            value,
+        }))
+    }
+}
+
+enum Pop<T> {
+    Data(T),
+    Empty,
+    Inconsistent,
+}
+
+// Producers push at `head`; the consumer pops after `tail`, which always points
+// at an already-consumed stub node.
+struct Queue<T> {
+    head: AtomicPtr<Node<T>>,
+    tail: UnsafeCell<*mut Node<T>>,
+}
+
+// SAFETY: values only move between threads through the queue, and `tail` is
+// only touched by the single consumer.
+unsafe impl<T: Send> Send for Queue<T> {}
+unsafe impl<T: Send> Sync for Queue<T> {}
+
+impl<T> Queue<T> {
+    fn new() -> Self {
+        let stub = Node::alloc(None);
+        Queue {
+            head: AtomicPtr::new(stub),
+            tail: UnsafeCell::new(stub),
+        }
+    }
+
+    fn push(&self, value: T) {
+        let node = Node::alloc(Some(value));
+        let prev = self.head.swap(node, Ordering::AcqRel);
+        // SAFETY: `prev` stays alive until the consumer moves past it, which
+        // cannot happen before its `next` is linked here.
+        unsafe { (*prev).next.store(node, Ordering::Release) };
+    }
+
+    fn is_empty(&self) -> bool {
+        // SAFETY: only called by the consumer.
+        let tail = unsafe { *self.tail.get() };
+        self.head.load(Ordering::Acquire) == tail
+    }
+
+    // SAFETY: must only be called by one thread at a time.
+    unsafe fn pop(&self) -> Pop<T> {
+        let tail = *self.tail.get();
+        let next = (*tail).next.load(Ordering::Acquire);
+        if !next.is_null() {
+            *self.tail.get() = next;
+            let value = (*next).value.take().expect("queued node without a value");
+            drop(Box::from_raw(tail));
+            return Pop::Data(value);
+        }
+        if self.head.load(Ordering::Acquire) == tail {
+            Pop::Empty
+        } else {
+            Pop::Inconsistent
+        }
+    }
+}
+
+impl<T> Drop for Queue<T> {
+    fn drop(&mut self) {
+        let mut node = *self.tail.get_mut();
+        while !node.is_null() {
+            // SAFETY: we have exclusive access, so every node is owned by the list.
+            let boxed = unsafe { Box::from_raw(node) };
+            node = boxed.next.load(Ordering::Relaxed);
+        }
+    }
+}
+
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        queue: Queue::new(),
+        senders: AtomicUsize::new(1),
+        closed: AtomicBool::new(false),
+        parked: AtomicBool::new(false),
+        receiver: Mutex::new(None),
+    });
+
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn ping_pong() {
+        let (tx, mut rx) = channel();
+        tx.send(25).unwrap();
+        assert_eq!(Some(25), rx.recv())
+    }
+
+    #[test]
+    pub fn send_drop() {
+        let (tx, mut rx) = channel::<()>();
+        drop(tx);
+
+        assert_eq!(rx.recv(), None)
+    }
+
+    #[test]
+    pub fn send_after_rx_close() {
+        let (tx, rx) = channel();
+        drop(rx);
+
+        assert_eq!(tx.send(5), Err(SendError(5)))
+    }
+
+    #[test]
+    pub fn try_recv_empty_then_disconnected() {
+        let (tx, mut rx) = channel();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+        tx.send(1).unwrap();
+        drop(tx);
+
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    pub fn many_producers_keep_per_producer_order() {
+        let (tx, rx) = channel();
+        let producers: Vec<_> = (0..4)
+            .map(|p| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for i in 0..1000 {
+                        tx.send((p, i)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+
+        let mut next = [0; 4];
+        for (p, i) in rx {
+            assert_eq!(next[p], i);
+            next[p] += 1;
+        }
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        assert_eq!(next, [1000; 4]);
+    }
+
+    #[test]
+    pub fn unreceived_values_are_dropped() {
+        let payload = Arc::new(());
+        let (tx, rx) = channel();
+        tx.send(Arc::clone(&payload)).unwrap();
+        tx.send(Arc::clone(&payload)).unwrap();
+        assert_eq!(Arc::strong_count(&payload), 3);
+
+        drop(rx);
+        drop(tx);
+        assert_eq!(Arc::strong_count(&payload), 1);
+    }
+}
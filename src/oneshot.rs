@@ -0,0 +1,175 @@
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::{RecvTimeoutError, SendError, TryRecvError};
+
+pub struct OneshotSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> OneshotSender<T> {
+    pub fn send(self, value: T) -> Result<(), SendError<T>> {
+        let mut inner = self.shared.lock();
+        if !inner.receiver_alive {
+            return Err(SendError(value));
+        }
+        inner.value = Some(value);
+        // dropping `self` on return wakes the receiver
+        Ok(())
+    }
+}
+
+impl<T> Drop for OneshotSender<T> {
+    fn drop(&mut self) {
+        let mut inner = self.shared.lock();
+        inner.sender_alive = false;
+        drop(inner);
+        self.shared.available.notify_one();
+    }
+}
+
+pub struct OneshotReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> OneshotReceiver<T> {
+    pub fn recv(mut self) -> Option<T> {
+        self.recv_until(None).ok()
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut inner = self.shared.lock();
+        match inner.value.take() {
+            Some(t) => Ok(t),
+            None if !inner.sender_alive => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.recv_until(Some(deadline)),
+            None => self.recv_until(None),
+        }
+    }
+
+    fn recv_until(&mut self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        let mut inner = self.shared.lock();
+        loop {
+            match inner.value.take() {
+                Some(t) => return Ok(t),
+                None if !inner.sender_alive => return Err(RecvTimeoutError::Disconnected),
+                None => match deadline {
+                    None => {
+                        inner = self
+                            .shared
+                            .available
+                            .wait(inner)
+                            .unwrap_or_else(PoisonError::into_inner)
+                    }
+                    Some(deadline) => {
+                        let remaining = deadline.saturating_duration_since(Instant::now());
+                        if remaining == Duration::from_secs(0) {
+                            return Err(RecvTimeoutError::Timeout);
+                        }
+                        inner = self
+                            .shared
+                            .available
+                            .wait_timeout(inner, remaining)
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .0;
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl<T> Drop for OneshotReceiver<T> {
+    fn drop(&mut self) {
+        let mut inner = self.shared.lock();
+        inner.receiver_alive = false;
+    }
+}
+
+struct Shared<T> {
+    inner: Mutex<Inner<T>>,
+    available: Condvar,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, Inner<T>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+struct Inner<T> {
+    value: Option<T>,
+    sender_alive: bool,
+    receiver_alive: bool,
+}
+
+pub fn oneshot<T>() -> (OneshotSender<T>, OneshotReceiver<T>) {
+    let shared = Arc::new(Shared {
+        inner: Mutex::new(Inner {
+            value: None,
+            sender_alive: true,
+            receiver_alive: true,
+        }),
+        available: Condvar::new(),
+    });
+
+    (
+        OneshotSender {
+            shared: shared.clone(),
+        },
+        OneshotReceiver { shared },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn send_then_recv() {
+        let (tx, rx) = oneshot();
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            tx.send("reply").unwrap();
+        });
+
+        assert_eq!(rx.recv(), Some("reply"));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    pub fn sender_dropped_without_sending() {
+        let (tx, mut rx) = oneshot::<()>();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(20)),
+            Err(RecvTimeoutError::Timeout)
+        );
+
+        drop(tx);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    pub fn value_is_taken_once() {
+        let (tx, mut rx) = oneshot();
+        tx.send(5).unwrap();
+
+        assert_eq!(rx.try_recv(), Ok(5));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    pub fn send_after_receiver_dropped() {
+        let (tx, rx) = oneshot();
+        drop(rx);
+        assert_eq!(tx.send(5), Err(SendError(5)));
+    }
+}
@@ -0,0 +1,207 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+use std::time::Instant;
+
+pub(crate) enum Queue<T> {
+    Fifo(VecDeque<T>),
+    Priority {
+        heap: BinaryHeap<Prioritized<T>>,
+        seq: u64,
+    },
+}
+
+impl<T> Queue<T> {
+    pub(crate) fn fifo(capacity: Option<usize>) -> Self {
+        match capacity {
+            Some(cap) => Queue::Fifo(VecDeque::with_capacity(cap)),
+            None => Queue::Fifo(VecDeque::new()),
+        }
+    }
+
+    pub(crate) fn priority() -> Self {
+        Queue::Priority {
+            heap: BinaryHeap::new(),
+            seq: 0,
+        }
+    }
+
+    // Moves every value out into a queue of the same kind, leaving this one empty.
+    pub(crate) fn take(&mut self) -> Self {
+        match self {
+            Queue::Fifo(queue) => Queue::Fifo(std::mem::take(queue)),
+            Queue::Priority { heap, seq } => Queue::Priority {
+                heap: std::mem::take(heap),
+                seq: *seq,
+            },
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Queue::Fifo(queue) => queue.len(),
+            Queue::Priority { heap, .. } => heap.len(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // FIFO queues ignore the priority.
+    pub(crate) fn push(&mut self, value: T, priority: u32) {
+        match self {
+            Queue::Fifo(queue) => queue.push_back(value),
+            Queue::Priority { heap, seq } => {
+                heap.push(Prioritized {
+                    priority,
+                    seq: *seq,
+                    value,
+                });
+                *seq += 1;
+            }
+        }
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        match self {
+            Queue::Fifo(queue) => queue.pop_front(),
+            Queue::Priority { heap, .. } => heap.pop().map(|p| p.value),
+        }
+    }
+
+    // Only rendezvous channels take back a value, and those are always FIFO.
+    pub(crate) fn pop_back(&mut self) -> Option<T> {
+        match self {
+            Queue::Fifo(queue) => queue.pop_back(),
+            Queue::Priority { .. } => unreachable!("priority queues are never rendezvous"),
+        }
+    }
+
+    // The receiver may only take a whole batch at once when order is
+    // plain FIFO.
+    pub(crate) fn as_fifo_mut(&mut self) -> Option<&mut VecDeque<T>> {
+        match self {
+            Queue::Fifo(queue) => Some(queue),
+            Queue::Priority { .. } => None,
+        }
+    }
+
+    pub(crate) fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        match self {
+            Queue::Fifo(queue) => queue.retain(|t| f(t)),
+            Queue::Priority { heap, .. } => heap.retain(|p| f(&p.value)),
+        }
+    }
+
+    pub(crate) fn pop_into<E: Extend<T>>(&mut self, out: &mut E, n: usize) {
+        match self {
+            Queue::Fifo(queue) => out.extend(queue.drain(..n.min(queue.len()))),
+            Queue::Priority { .. } => out.extend(std::iter::from_fn(|| self.pop()).take(n)),
+        }
+    }
+}
+
+pub(crate) struct Prioritized<T> {
+    priority: u32,
+    seq: u64,
+    value: T,
+}
+
+// Higher priorities come first; within a priority, earlier sends come first.
+impl<T> Ord for Prioritized<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl<T> PartialOrd for Prioritized<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> PartialEq for Prioritized<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Prioritized<T> {}
+
+// Values waiting for their due time, earliest first.
+pub(crate) struct Schedule<T> {
+    heap: BinaryHeap<Scheduled<T>>,
+    seq: u64,
+}
+
+impl<T> Schedule<T> {
+    pub(crate) fn new() -> Self {
+        Schedule {
+            heap: BinaryHeap::new(),
+            seq: 0,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, due: Instant, value: T) {
+        self.heap.push(Scheduled {
+            due,
+            seq: self.seq,
+            value,
+        });
+        self.seq += 1;
+    }
+
+    pub(crate) fn next_due(&self) -> Option<Instant> {
+        self.heap.peek().map(|s| s.due)
+    }
+
+    pub(crate) fn pop_due(&mut self, now: Instant) -> Option<T> {
+        if self.next_due()? > now {
+            return None;
+        }
+        self.heap.pop().map(|s| s.value)
+    }
+
+    pub(crate) fn take(&mut self) -> Self {
+        Schedule {
+            heap: std::mem::take(&mut self.heap),
+            seq: self.seq,
+        }
+    }
+}
+
+pub(crate) struct Scheduled<T> {
+    due: Instant,
+    seq: u64,
+    value: T,
+}
+
+// Reversed so the max-heap yields the earliest due time; ties go in send order.
+impl<T> Ord for Scheduled<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .due
+            .cmp(&self.due)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl<T> PartialOrd for Scheduled<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> PartialEq for Scheduled<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Scheduled<T> {}
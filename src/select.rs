@@ -0,0 +1,205 @@
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::task::{Wake, Waker};
+
+use crate::Receiver;
+
+pub(crate) trait Selectable {
+    // Returns true if receiving would not block, otherwise arranges for `waker`
+    // (if any) to be woken once it might not.
+    fn poll_ready(&self, waker: Option<&Waker>) -> bool;
+
+    // Forgets a waker registered by `poll_ready` that is no longer waiting.
+    fn unregister(&self, waker: &Waker);
+}
+
+#[derive(Default)]
+struct Signal {
+    notified: Mutex<bool>,
+    cond: Condvar,
+}
+
+impl Signal {
+    fn wait(&self) {
+        let mut notified = self.notified.lock().unwrap_or_else(PoisonError::into_inner);
+        while !*notified {
+            notified = self
+                .cond
+                .wait(notified)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        *notified = false;
+    }
+}
+
+impl Wake for Signal {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        *self.notified.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        self.cond.notify_one();
+    }
+}
+
+#[derive(Default)]
+pub struct Select<'a> {
+    handles: Vec<&'a dyn Selectable>,
+    next_start: usize,
+}
+
+impl<'a> Select<'a> {
+    pub fn new() -> Self {
+        Select::default()
+    }
+
+    pub fn recv<T>(&mut self, receiver: &'a Receiver<T>) -> usize {
+        self.handles.push(receiver);
+        self.handles.len() - 1
+    }
+
+    // Blocks until one of the receivers has a value or is disconnected and
+    // returns its index. With cloned receivers another consumer may still
+    // take the value first.
+    pub fn ready(&mut self) -> usize {
+        assert!(!self.handles.is_empty(), "no receivers added to Select");
+        let signal = Arc::new(Signal::default());
+        let waker = Waker::from(Arc::clone(&signal));
+        let index = loop {
+            if let Some(index) = self.poll(Some(&waker)) {
+                break index;
+            }
+            signal.wait();
+        };
+        // the channels that were not ready would otherwise keep the waker
+        // until their next send
+        for handle in &self.handles {
+            handle.unregister(&waker);
+        }
+        index
+    }
+
+    pub fn try_ready(&mut self) -> Option<usize> {
+        self.poll(None)
+    }
+
+    fn poll(&mut self, waker: Option<&Waker>) -> Option<usize> {
+        // rotate the starting point so a busy channel cannot starve the rest
+        let len = self.handles.len();
+        let start = self.next_start % len;
+        self.next_start = self.next_start.wrapping_add(1);
+        (0..len)
+            .map(|offset| (start + offset) % len)
+            .find(|&index| self.handles[index].poll_ready(waker))
+    }
+}
+
+#[macro_export]
+macro_rules! select {
+    ($(recv($rx:expr) -> $res:pat => $body:expr),+ $(,)?) => {{
+        let index = {
+            let mut sel = $crate::Select::new();
+            $( sel.recv(&$rx); )+
+            sel.ready()
+        };
+        $crate::select!(@arm index () $( ($rx, $res, $body) )+)
+    }};
+    (@arm $index:ident ($($n:tt)*) ($rx:expr, $res:pat, $body:expr) $($rest:tt)*) => {
+        if $index == 0 $(+ $n)* {
+            let $res = $rx.recv();
+            $body
+        } else {
+            $crate::select!(@arm $index ($($n)* 1) $($rest)*)
+        }
+    };
+    (@arm $index:ident ($($n:tt)*)) => {
+        unreachable!()
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::channel;
+
+    #[test]
+    pub fn ready_picks_channel_with_value() {
+        let (_tx1, rx1) = channel::<i32>();
+        let (tx2, mut rx2) = channel();
+
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            tx2.send(2).unwrap();
+            tx2
+        });
+        let mut sel = Select::new();
+        assert_eq!(sel.recv(&rx1), 0);
+        assert_eq!(sel.recv(&rx2), 1);
+        assert_eq!(sel.try_ready(), None);
+        assert_eq!(sel.ready(), 1);
+        drop(sel);
+
+        assert_eq!(rx2.recv(), Some(2));
+        drop(handle.join().unwrap());
+        drop(rx1);
+    }
+
+    #[test]
+    pub fn select_macro_reports_disconnect() {
+        let (data_tx, mut data) = channel();
+        let (shutdown_tx, mut shutdown) = channel::<()>();
+        data_tx.send(1).unwrap();
+
+        let first = select! {
+            recv(data) -> msg => msg,
+            recv(shutdown) -> _ => None,
+        };
+        assert_eq!(first, Some(1));
+
+        drop(shutdown_tx);
+        let stopped = select! {
+            recv(shutdown) -> msg => msg.is_none(),
+            recv(data) -> _ => false,
+        };
+        assert!(stopped);
+        drop(data_tx);
+    }
+
+    #[test]
+    pub fn ready_waits_for_delayed_value() {
+        let (_tx1, rx1) = channel::<i32>();
+        let (tx2, mut rx2) = channel();
+        tx2.send_after(2, Duration::from_millis(20)).unwrap();
+
+        let mut sel = Select::new();
+        sel.recv(&rx1);
+        sel.recv(&rx2);
+        assert_eq!(sel.try_ready(), None);
+        assert_eq!(sel.ready(), 1);
+        drop(sel);
+        assert_eq!(rx2.try_recv(), Ok(2));
+    }
+
+    #[test]
+    pub fn ready_leaves_no_wakers_behind() {
+        let (_shutdown_tx, mut shutdown) = channel::<()>();
+        let (data_tx, mut data) = channel();
+        for i in 0..100 {
+            data_tx.send(i).unwrap();
+            let received = select! {
+                recv(shutdown) -> _ => None,
+                recv(data) -> msg => msg,
+            };
+            assert_eq!(received, Some(i));
+        }
+
+        let mut sel = Select::new();
+        sel.recv(&shutdown);
+        assert_eq!(sel.try_ready(), None);
+        drop(sel);
+        assert!(shutdown.shared.lock().wakers.is_empty());
+    }
+}
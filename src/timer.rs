@@ -0,0 +1,227 @@
+//! A single background thread that wakes tasks at a given instant.
+//!
+//! Blocking receivers can sleep on a condvar with a timeout, but async
+//! receivers and `Select` only have a waker, so something else has to call it
+//! once a delayed value comes due. Each channel owns an `Alarm` so that it has
+//! at most one entry armed per due time no matter how often it is polled.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Once, OnceLock, PoisonError};
+use std::task::{Wake, Waker};
+use std::time::Instant;
+
+struct Entry {
+    at: Instant,
+    waker: Waker,
+}
+
+// Earliest deadline first.
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.at.cmp(&self.at)
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.at == other.at
+    }
+}
+
+impl Eq for Entry {}
+
+#[derive(Default)]
+struct Timer {
+    entries: Mutex<BinaryHeap<Entry>>,
+    cond: Condvar,
+}
+
+impl Timer {
+    fn run(&self) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        loop {
+            let now = Instant::now();
+            let mut due = Vec::new();
+            while entries.peek().is_some_and(|entry| entry.at <= now) {
+                due.extend(entries.pop().map(|entry| entry.waker));
+            }
+            if !due.is_empty() {
+                drop(entries);
+                for waker in due {
+                    waker.wake();
+                }
+                entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+                continue;
+            }
+            entries = match entries.peek().map(|entry| entry.at - now) {
+                Some(remaining) => {
+                    self.cond
+                        .wait_timeout(entries, remaining)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+                None => self
+                    .cond
+                    .wait(entries)
+                    .unwrap_or_else(PoisonError::into_inner),
+            };
+        }
+    }
+}
+
+fn timer() -> &'static Timer {
+    static TIMER: OnceLock<Timer> = OnceLock::new();
+    static START: Once = Once::new();
+    let timer = TIMER.get_or_init(Timer::default);
+    START.call_once(|| {
+        std::thread::Builder::new()
+            .name("channels-timer".into())
+            .spawn(move || timer.run())
+            .expect("failed to spawn timer thread");
+    });
+    timer
+}
+
+fn wake_at(at: Instant, waker: Waker) {
+    let timer = timer();
+    let mut entries = timer.entries.lock().unwrap_or_else(PoisonError::into_inner);
+    let earliest = entries.peek().is_none_or(|entry| at < entry.at);
+    entries.push(Entry { at, waker });
+    drop(entries);
+    if earliest {
+        timer.cond.notify_one();
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Alarm {
+    state: Mutex<AlarmState>,
+}
+
+#[derive(Default)]
+struct AlarmState {
+    // the earliest time a timer entry is pending for
+    armed: Option<Instant>,
+    wakers: Vec<Waker>,
+}
+
+impl Alarm {
+    fn lock(&self) -> MutexGuard<'_, AlarmState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Wakes `waker` at `at` or earlier. The timer is only re-armed when `at`
+    // is earlier than the time already armed.
+    pub(crate) fn wake_at(self: &Arc<Self>, at: Instant, waker: &Waker) {
+        let mut state = self.lock();
+        if !state.wakers.iter().any(|w| w.will_wake(waker)) {
+            state.wakers.push(waker.clone());
+        }
+        if state.armed.is_some_and(|armed| armed <= at) {
+            return;
+        }
+        state.armed = Some(at);
+        drop(state);
+        wake_at(at, Waker::from(Arc::clone(self)));
+    }
+
+    pub(crate) fn cancel(&self, waker: &Waker) {
+        self.lock().wakers.retain(|w| !w.will_wake(waker));
+    }
+
+    pub(crate) fn clear(&self) {
+        self.lock().wakers.clear();
+    }
+}
+
+impl Wake for Alarm {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let mut state = self.lock();
+        state.armed = None;
+        let wakers = std::mem::take(&mut state.wakers);
+        drop(state);
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    pub fn wakes_in_deadline_order() {
+        let late = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let early = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let now = Instant::now();
+        wake_at(
+            now + Duration::from_millis(200),
+            Waker::from(Arc::clone(&late)),
+        );
+        wake_at(
+            now + Duration::from_millis(20),
+            Waker::from(Arc::clone(&early)),
+        );
+
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(early.0.load(Ordering::SeqCst), 1);
+        assert_eq!(late.0.load(Ordering::SeqCst), 0);
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(late.0.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    pub fn alarm_arms_timer_once_per_due_time() {
+        let alarm = Arc::new(Alarm::default());
+        let counter = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(Arc::clone(&counter));
+        let at = Instant::now() + Duration::from_secs(3600);
+        for _ in 0..100 {
+            alarm.wake_at(at, &waker);
+        }
+
+        let alarm_waker = Waker::from(Arc::clone(&alarm));
+        let armed = |timer: &Timer| {
+            let entries = timer.entries.lock().unwrap();
+            entries
+                .iter()
+                .filter(|entry| entry.waker.will_wake(&alarm_waker))
+                .count()
+        };
+        assert_eq!(armed(timer()), 1);
+        assert_eq!(alarm.lock().wakers.len(), 1);
+
+        // an earlier due time re-arms, and firing wakes every waiting waker
+        alarm.wake_at(Instant::now() + Duration::from_millis(20), &waker);
+        assert_eq!(armed(timer()), 2);
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+        assert!(alarm.lock().wakers.is_empty());
+
+        alarm.wake_at(at, &waker);
+        alarm.cancel(&waker);
+        assert!(alarm.lock().wakers.is_empty());
+    }
+}
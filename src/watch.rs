@@ -0,0 +1,225 @@
+//! A channel that only keeps the latest value.
+//!
+//! Each `send` overwrites the slot and bumps its version; receivers can look at
+//! the current value at any time or block until a newer version arrives.
+
+use std::error::Error;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+
+use crate::SendError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvError;
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "receiving on a closed channel")
+    }
+}
+
+impl Error for RecvError {}
+
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        let mut inner = self.shared.lock();
+        inner.senders += 1;
+        Sender {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut inner = self.shared.lock();
+        inner.senders -= 1;
+        let was_last = inner.senders == 0;
+        drop(inner);
+        if was_last {
+            self.shared.available.notify_all();
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let mut inner = self.shared.lock();
+        if inner.receivers == 0 {
+            return Err(SendError(value));
+        }
+        inner.value = value;
+        inner.version += 1;
+        drop(inner);
+        self.shared.available.notify_all();
+        Ok(())
+    }
+
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref {
+            inner: self.shared.lock(),
+        }
+    }
+}
+
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    seen: u64,
+}
+
+impl<T> Receiver<T> {
+    // Holds the channel lock until dropped, so keep the borrow short.
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref {
+            inner: self.shared.lock(),
+        }
+    }
+
+    pub fn has_changed(&self) -> bool {
+        self.shared.lock().version != self.seen
+    }
+
+    // Blocks until a version newer than the last one seen by this receiver is
+    // sent, and marks it seen. Fails once every sender is gone.
+    pub fn changed(&mut self) -> Result<(), RecvError> {
+        let mut inner = self.shared.lock();
+        loop {
+            if inner.version != self.seen {
+                self.seen = inner.version;
+                return Ok(());
+            }
+            if inner.senders == 0 {
+                return Err(RecvError);
+            }
+            inner = self
+                .shared
+                .available
+                .wait(inner)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        let mut inner = self.shared.lock();
+        inner.receivers += 1;
+        Receiver {
+            shared: Arc::clone(&self.shared),
+            seen: self.seen,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut inner = self.shared.lock();
+        inner.receivers -= 1;
+    }
+}
+
+pub struct Ref<'a, T> {
+    inner: MutexGuard<'a, Inner<T>>,
+}
+
+impl<T> Deref for Ref<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner.value
+    }
+}
+
+struct Shared<T> {
+    inner: Mutex<Inner<T>>,
+    available: Condvar,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, Inner<T>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+struct Inner<T> {
+    value: T,
+    version: u64,
+    senders: usize,
+    receivers: usize,
+}
+
+pub fn watch<T>(initial: T) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        inner: Mutex::new(Inner {
+            value: initial,
+            version: 0,
+            senders: 1,
+            receivers: 1,
+        }),
+        available: Condvar::new(),
+    });
+
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared, seen: 0 },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn borrow_sees_latest_value() {
+        let (tx, rx) = watch(1);
+        assert_eq!(*rx.borrow(), 1);
+        assert!(!rx.has_changed());
+
+        tx.send(2).unwrap();
+        tx.send(3).unwrap();
+        assert!(rx.has_changed());
+        assert_eq!(*rx.borrow(), 3);
+        assert_eq!(*tx.borrow(), 3);
+    }
+
+    #[test]
+    pub fn changed_skips_intermediate_values() {
+        let (tx, mut rx) = watch("initial");
+        tx.send("a").unwrap();
+        tx.send("b").unwrap();
+
+        assert_eq!(rx.changed(), Ok(()));
+        assert_eq!(*rx.borrow(), "b");
+        assert!(!rx.has_changed());
+    }
+
+    #[test]
+    pub fn changed_blocks_until_send() {
+        let (tx, mut rx) = watch(0);
+        let handle = std::thread::spawn(move || {
+            rx.changed().unwrap();
+            let value = *rx.borrow();
+            (value, rx.changed())
+        });
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        tx.send(7).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        drop(tx);
+
+        assert_eq!(handle.join().unwrap(), (7, Err(RecvError)));
+    }
+
+    #[test]
+    pub fn send_without_receivers_fails() {
+        let (tx, rx) = watch(0);
+        drop(rx);
+        assert_eq!(tx.send(1), Err(SendError(1)));
+    }
+}